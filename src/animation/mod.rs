@@ -1,5 +1,5 @@
-
 use amethyst::{
+    assets::{AssetStorage},
     core::{
         transform::{Transform}
     },
@@ -8,8 +8,10 @@ use amethyst::{
     renderer::{SpriteRender},
     core::timing::{Time}
 };
+use serde::{Deserialize};
 
-use crate::character::{CharacterType};
+use crate::character::{CharacterName, CharacterData, CharacterResource};
+use crate::combat::{HitStop, Frozen};
 use crate::system::{Physics};
 
 #[derive(Clone, Default, Debug)]
@@ -30,36 +32,18 @@ impl AnimationData {
     }
 }
 
-#[derive(Default, Debug, PartialEq)]
-pub struct AnimationResource;
-impl AnimationResource {
-    pub fn data(&self, char_type: &CharacterType, anim_type: &AnimationType) -> AnimationData {
-        match char_type {
-            //default implementation - temp for enemy
-            CharacterType::Player => { match anim_type {
-                AnimationType::Idle => AnimationData::new(vec![0,1,2,3], 1./6., AnimationType::Idle, true),
-                AnimationType::Run => AnimationData::new(vec![4,5,6,7,8,9], 1./10., AnimationType::Run, true),
-                AnimationType::Jump(falling, running) => { match running {
-                    true => AnimationData::new(vec![31,32,33,34], 1./10., AnimationType::Jump(*falling, true), true),
-                    false => match falling {
-                        false => AnimationData::new(vec![10,11,12,13], 1./10., AnimationType::Jump(false, *running), false),
-                        true => AnimationData::new(vec![13,12,11], 1./10., AnimationType::Jump(true, *running), false)
-                    }
-                } },
-                AnimationType::Attack(combo) => { match combo {
-                    1 => AnimationData::new(vec![19,20,21,22,23,24], 1./10., AnimationType::Attack(1), true),
-                    2 => AnimationData::new(vec![25,26,27,28,29,30], 1./10., AnimationType::Attack(2), true),
-                    _ => AnimationData::new(vec![14,15,16,17,18], 1./10., AnimationType::Attack(*combo), true)
-                } }
-            } },
-            CharacterType::Enemy => { match anim_type {
-                AnimationType::Idle => AnimationData::new(vec![0,1,2,3], 1./6., AnimationType::Idle, true),
-                AnimationType::Run => AnimationData::new(vec![4,5,6,7,8,9], 1./10., AnimationType::Run, true),
-                AnimationType::Attack(combo) => AnimationData::new(vec![10,11,12,13,14], 1./10., AnimationType::Attack(*combo), true),
-                _ => AnimationData::new(vec![0,1,2,3], 1./6., anim_type.clone(), true) //default to idle animation
-            } }
-        }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum LoopMode {
+    Loop,
+    //stops on the last frame
+    Once
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnimationDefinition {
+    pub frames: Vec<usize>,
+    pub frame_duration: f32,
+    pub mode: LoopMode
 }
 
 #[derive(Default, Debug)]
@@ -95,6 +79,10 @@ impl SpriteAnimation {
     pub fn get_frame(&self) -> usize {
         self.frames[self.current_frame]
     }
+    //position within the animation, as opposed to the sprite number
+    pub fn frame_index(&self) -> usize {
+        self.current_frame
+    }
 }
 impl Component for SpriteAnimation {
     type Storage = DenseVecStorage<Self>;
@@ -105,7 +93,36 @@ pub enum AnimationType {
     Idle,
     Run,
     Jump(bool, bool),
-    Attack(u8)
+    Attack(u8),
+    Hurt,
+    Die,
+    WallSlide,
+    AirJump,
+    Dash,
+    Crouch,
+    Slide,
+    Climb
+}
+impl AnimationType {
+    //key into a character's animations, boxes and attacks, along with a more general one to fall back on
+    pub fn keys(&self) -> (String, &'static str) {
+        match self {
+            AnimationType::Idle => ("idle".to_string(), "idle"),
+            AnimationType::Run => ("run".to_string(), "idle"),
+            AnimationType::Jump(_, true) => ("running_jump".to_string(), "jump"),
+            AnimationType::Jump(false, false) => ("jump".to_string(), "idle"),
+            AnimationType::Jump(true, false) => ("fall".to_string(), "jump"),
+            AnimationType::Attack(combo) => (format!("attack_{}", combo), "attack"),
+            AnimationType::Hurt => ("hurt".to_string(), "idle"),
+            AnimationType::Die => ("die".to_string(), "idle"),
+            AnimationType::WallSlide => ("wall_slide".to_string(), "idle"),
+            AnimationType::AirJump => ("air_jump".to_string(), "jump"),
+            AnimationType::Dash => ("dash".to_string(), "run"),
+            AnimationType::Crouch => ("crouch".to_string(), "idle"),
+            AnimationType::Slide => ("slide".to_string(), "crouch"),
+            AnimationType::Climb => ("climb".to_string(), "idle")
+        }
+    }
 }
 impl Default for AnimationType {
     fn default() -> Self { AnimationType::Idle }
@@ -118,26 +135,37 @@ pub struct AnimationSystem;
 impl <'a> System<'a> for AnimationSystem {
     type SystemData = (
         ReadStorage<'a, Physics>,
-        ReadStorage<'a, CharacterType>,
+        ReadStorage<'a, CharacterName>,
         WriteStorage<'a, Transform>,
         WriteStorage<'a, SpriteRender>,
         WriteStorage<'a, SpriteAnimation>,
         ReadStorage<'a, AnimationType>,
-        Read<'a, AnimationResource>,
+        ReadStorage<'a, Frozen>,
+        Read<'a, CharacterResource>,
+        Read<'a, AssetStorage<CharacterData>>,
+        Read<'a, HitStop>,
         Read<'a, Time>,
     );
-    fn run(&mut self, (physics_set, character_types, mut transforms, mut sprite_renders, mut animations, anim_types, animation_resource, time): Self::SystemData) {
-        for (physics, char_type, transform, sprite_render, anim, anim_type) in (&physics_set, &character_types, &mut transforms, &mut sprite_renders, &mut animations, &anim_types).join() {
+    fn run(&mut self, (physics_set, character_names, mut transforms, mut sprite_renders, mut animations, anim_types, frozen_set, character_resource, character_data, hit_stop, time): Self::SystemData) {
+        for (physics, name, transform, sprite_render, anim, anim_type, frozen) in (&physics_set, &character_names, &mut transforms, &mut sprite_renders, &mut animations, &anim_types, frozen_set.maybe()).join() {
             //create a new sprite object only if the animation has changed
             if anim.animation_type != *anim_type {
-                *anim = SpriteAnimation::from_data(animation_resource.data(char_type, anim_type));
+                *anim = SpriteAnimation::from_data(character_resource.animation(&character_data, name, anim_type));
+                sprite_render.sprite_number = anim.get_frame();
             }
             //rotate sprite depending on direction we're facing
-            if physics.velocity.x > 0.1 {
+            if physics.facing > 0. {
                 transform.set_rotation_y_axis(0.);
-            } else if physics.velocity.x < -0.1 {
+            } else {
                 transform.set_rotation_y_axis(std::f32::consts::PI);
             }
+            //hold the current frame during hit-stop, and on a ladder while not moving
+            if hit_stop.is_active() || frozen.is_some() {
+                continue;
+            }
+            if *anim_type == AnimationType::Climb && physics.velocity.norm() < 1. {
+                continue;
+            }
             //progress each animation to the next frame
             anim.elapsed_time += time.delta_seconds();
             let frame_count = if anim.loop_anim {
@@ -147,10 +175,11 @@ impl <'a> System<'a> for AnimationSystem {
                 //don't loop animation, just stop at the last frame
                 ((anim.elapsed_time / anim.time_per_frame) as usize).min(anim.frames.len()-1)
             };
+            //done once the last frame has had its full time, however many frames there are
+            if anim.elapsed_time >= anim.time_per_frame*anim.frames.len() as f32 {
+                anim.finished = true;
+            }
             if frame_count != anim.current_frame {
-                if !anim.finished && frame_count == anim.frames.len()-1 {
-                    anim.finished = true;
-                }
                 anim.current_frame = frame_count;
                 sprite_render.sprite_number = anim.get_frame();
             }
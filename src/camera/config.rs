@@ -0,0 +1,54 @@
+use serde::{Serialize, Deserialize};
+
+//loaded from config/camera.ron
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraConfig {
+    //world pixels shown at zoom 1, whatever the window size
+    pub viewport: (f32, f32),
+    pub zoom: f32,
+    //scale by whole numbers only and snap the camera and sprites to world pixels
+    pub pixel_perfect: bool,
+    //black bars over anything outside the viewport, otherwise the spare room shows more of the map
+    pub letterbox: bool,
+    //the player moves this freely around the focus before the camera follows
+    pub dead_zone: (f32, f32),
+    //pixels the camera leads in the direction the player is running
+    pub look_ahead: f32,
+    pub look_ahead_speed: f32,
+    //only follow vertically on landing, unless the player leaves the dead zone
+    pub vertical_lock: bool,
+    //spring pulling the camera to its target, twice the square root of stiffness is critically damped
+    pub stiffness: f32,
+    pub damping: f32,
+    //seconds taken to pan into a new camera region
+    pub region_transition: f32,
+    //hold the game still during the pan, like walking through a door between rooms
+    pub freeze_transitions: bool,
+    //room kept around the players when the shared view zooms out to hold them all
+    pub coop_margin: f32,
+    //widest the shared view zooms out before splitting
+    pub coop_min_zoom: f32,
+    //give each player their own half of the window once they are too far apart for the shared view
+    pub split_screen: bool
+}
+impl Default for CameraConfig {
+    fn default() -> CameraConfig {
+        CameraConfig {
+            viewport: (400., 300.),
+            zoom: 1.,
+            pixel_perfect: true,
+            letterbox: true,
+            dead_zone: (32., 48.),
+            look_ahead: 40.,
+            look_ahead_speed: 3.,
+            vertical_lock: true,
+            stiffness: 60.,
+            damping: 15.5,
+            region_transition: 0.6,
+            freeze_transitions: false,
+            coop_margin: 48.,
+            coop_min_zoom: 0.6,
+            split_screen: true
+        }
+    }
+}
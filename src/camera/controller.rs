@@ -0,0 +1,198 @@
+use amethyst::{
+    core::{
+        math as na
+    },
+    ecs::{System, SystemData, Resources, Entity},
+    ecs::prelude::{Read, Write, ReadStorage},
+    shrev::{EventChannel, ReaderId}
+};
+
+use crate::character::{Player};
+use crate::environment::{Trigger};
+use crate::event::{GameEvent};
+use crate::tilemap::{TileMap};
+use super::{CameraSettings};
+
+type Vector3 = na::Vector3<f32>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    //slow in and out
+    Smooth
+}
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::Smooth => t*t*(3. - 2.*t)
+        }
+    }
+}
+
+//moves along a polyline at constant speed, the easing shapes progress over the whole path
+#[derive(Debug, Clone)]
+pub struct CameraPath {
+    pub points: Vec<Vector3>,
+    pub duration: f32,
+    pub easing: Easing,
+    //cutscene paths hand the camera back by themselves
+    pub pop_when_done: bool,
+    elapsed: f32
+}
+impl CameraPath {
+    pub fn new(points: Vec<Vector3>, duration: f32, easing: Easing, pop_when_done: bool) -> CameraPath {
+        CameraPath {
+            points,
+            duration,
+            easing,
+            pop_when_done,
+            elapsed: 0.
+        }
+    }
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+    pub fn advance(&mut self, dt: f32) -> Vector3 {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        let t = if self.duration > 0. { self.elapsed/self.duration } else { 1. };
+        self.point_at(self.easing.apply(t))
+    }
+    fn point_at(&self, t: f32) -> Vector3 {
+        let first = match self.points.first() {
+            Some(first) => *first,
+            None => return Vector3::new(0., 0., 0.)
+        };
+        let length: f32 = self.points.windows(2).map(|pair| (pair[1] - pair[0]).norm()).sum();
+        let mut distance = length*t;
+        for pair in self.points.windows(2) {
+            let segment = (pair[1] - pair[0]).norm();
+            if distance <= segment && segment > 0. {
+                return pair[0] + (pair[1] - pair[0])*(distance/segment);
+            }
+            distance -= segment;
+        }
+        *self.points.last().unwrap_or(&first)
+    }
+}
+
+//what drives the camera, CameraSettings keeps a stack of these
+#[derive(Debug, Clone)]
+pub enum CameraController {
+    //the player, with dead zone, look-ahead and regions
+    Follow,
+    Fixed(Vector3),
+    Path(CameraPath),
+    //keeps another entity in the middle of the view
+    Focus(Entity)
+}
+
+//turns the player's trigger actions into camera moves:
+//"camera_path:NAME" runs along the polyline NAME, taking "duration" from it,
+//"camera_fixed" holds on the trigger while the player is inside,
+//"camera_zoom:VALUE" zooms while the player is inside
+#[derive(Default)]
+pub struct CameraScriptSystem {
+    event_reader: Option<ReaderId<GameEvent>>
+}
+impl <'a> System<'a> for CameraScriptSystem {
+    type SystemData = (
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Trigger>,
+        Write<'a, CameraSettings>,
+        Read<'a, TileMap>,
+        Read<'a, EventChannel<GameEvent>>,
+    );
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.event_reader = Some(res.fetch_mut::<EventChannel<GameEvent>>().register_reader());
+    }
+    fn run(&mut self, (players, triggers, mut camera_settings, tilemap, game_events): Self::SystemData) {
+        for event in game_events.read(self.event_reader.as_mut().unwrap()) {
+            match event {
+                GameEvent::EnteredTrigger { entity, trigger, action } if players.contains(*entity) => {
+                    let (command, argument) = match action.find(':') {
+                        Some(split) => (&action[..split], &action[split + 1..]),
+                        None => (action.as_str(), "")
+                    };
+                    match command {
+                        "camera_path" => {
+                            //walking back in doesn't restart a cutscene that is still running
+                            if let Some(CameraController::Path(_)) = camera_settings.controllers.last() {
+                                continue;
+                            }
+                            if let Some(object) = tilemap.object_named(argument) {
+                                let duration = object.property("duration")
+                                    .and_then(|value| value.as_f64())
+                                    .map_or(3., |value| value as f32);
+                                let path = CameraPath::new(tilemap.object_path(object), duration, Easing::Smooth, true);
+                                camera_settings.push_controller(CameraController::Path(path));
+                            }
+                        },
+                        "camera_fixed" => {
+                            if let Some(trigger) = triggers.get(*trigger) {
+                                let area = &trigger.area;
+                                let centre = Vector3::new(area.position.x + area.size.x/2., area.position.y - area.size.y/2., 0.);
+                                camera_settings.push_controller(CameraController::Fixed(centre));
+                            }
+                        },
+                        "camera_zoom" => {
+                            if let Ok(zoom) = argument.parse::<f32>() {
+                                camera_settings.zoom_to(zoom, 1.);
+                            }
+                        },
+                        _ => {}
+                    }
+                },
+                GameEvent::ExitedTrigger { entity, action, .. } if players.contains(*entity) => {
+                    if action == "camera_fixed" {
+                        if let Some(CameraController::Fixed(_)) = camera_settings.controllers.last() {
+                            camera_settings.pop_controller();
+                        }
+                    } else if action.starts_with("camera_zoom:") {
+                        camera_settings.zoom_to(1., 1.);
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //70 pixels long, the corner is 30 in
+    fn path(easing: Easing) -> CameraPath {
+        let points = vec![Vector3::new(0., 0., 0.), Vector3::new(30., 0., 0.), Vector3::new(30., 40., 0.)];
+        CameraPath::new(points, 4., easing, false)
+    }
+
+    #[test]
+    fn linear_paths_keep_a_constant_speed_around_corners() {
+        let path = path(Easing::Linear);
+        assert_eq!(path.point_at(0.), Vector3::new(0., 0., 0.));
+        assert_eq!(path.point_at(0.5), Vector3::new(30., 5., 0.));
+        assert_eq!(path.point_at(1.), Vector3::new(30., 40., 0.));
+    }
+
+    #[test]
+    fn smooth_paths_start_slow_and_meet_linear_ones_halfway() {
+        let (mut linear, mut smooth) = (path(Easing::Linear), path(Easing::Smooth));
+        let (a, b) = (linear.advance(1.), smooth.advance(1.));
+        assert!(b.x < a.x);
+        assert!((smooth.advance(1.) - linear.advance(1.)).norm() < 0.001);
+        //both land on the last point and stay there
+        linear.advance(5.);
+        assert_eq!(smooth.advance(5.), Vector3::new(30., 40., 0.));
+        assert!(linear.finished() && smooth.finished());
+    }
+
+    #[test]
+    fn repeated_points_are_skipped() {
+        let points = vec![Vector3::new(0., 0., 0.), Vector3::new(0., 0., 0.), Vector3::new(10., 0., 0.)];
+        let path = CameraPath::new(points, 1., Easing::Linear, false);
+        assert_eq!(path.point_at(0.5), Vector3::new(5., 0., 0.));
+    }
+}
@@ -0,0 +1,580 @@
+use amethyst::{
+    assets::{AssetStorage, Loader},
+    core::{
+        math as na,
+        timing::{Time},
+        transform::{Transform},
+        Parent
+    },
+    ecs::{Component, System, Join, DenseVecStorage, Entity},
+    ecs::prelude::{Read, ReadExpect, Write, ReadStorage, WriteStorage},
+    prelude::*,
+    renderer::{
+        camera::{Camera, Projection},
+        loaders::{load_from_srgba},
+        palette::{Srgba},
+        Sprite, SpriteRender, SpriteSheet, Texture
+    },
+    window::{ScreenDimensions}
+};
+
+use crate::character::{Player};
+use crate::combat::{HitStop};
+use crate::hitbox::{Hitbox};
+use crate::system::{Physics};
+
+mod config;
+mod controller;
+mod render;
+
+pub use self::config::{CameraConfig};
+pub use self::controller::{CameraController, CameraPath, Easing, CameraScriptSystem};
+pub use self::render::{RenderSplitScreen};
+
+type Point3 = na::Point3<f32>;
+type Vector3 = na::Vector3<f32>;
+
+#[derive(Debug)]
+pub struct CameraSettings {
+    //the whole map, used while the players are outside every region
+    pub bounds: Hitbox,
+    //rooms from the map's "camera" objects, the view stays inside the one holding the player
+    pub regions: Vec<Hitbox>,
+    pub region: Option<usize>,
+    //the area the players start in is picked up without a pan
+    placed: bool,
+    //the top controller drives the camera, following the player when empty
+    pub controllers: Vec<CameraController>,
+    //where the camera is headed this frame, focus plus look-ahead
+    pub target: Vector3,
+    //camera centre before shake and pixel snapping
+    pub position: Vector3,
+    pub velocity: Vector3,
+    //world area visible through the letterbox, kept up to date by the viewport system
+    pub viewport: (f32, f32),
+    //runtime zoom on top of the configured one
+    pub zoom: f32,
+    zoom_from: f32,
+    zoom_target: f32,
+    zoom_duration: f32,
+    zoom_elapsed: f32,
+    //extra zoom out so the shared view holds every player, 1 with a single player
+    pub framing_zoom: f32,
+    //players are too far apart to share the view, each split view follows its own player
+    pub split: bool,
+    //area the view is currently kept inside, eased between regions
+    area: Hitbox,
+    area_from: Hitbox,
+    transition: f32,
+    transition_duration: f32
+}
+impl CameraSettings {
+    pub fn new(target: Vector3, bounds: Hitbox) -> CameraSettings {
+        CameraSettings {
+            target,
+            regions: Vec::new(),
+            region: None,
+            placed: false,
+            controllers: Vec::new(),
+            position: target,
+            velocity: Vector3::new(0., 0., 0.),
+            viewport: (400., 300.),
+            zoom: 1.,
+            zoom_from: 1.,
+            zoom_target: 1.,
+            zoom_duration: 0.,
+            zoom_elapsed: 0.,
+            framing_zoom: 1.,
+            split: false,
+            area: bounds.clone(),
+            area_from: bounds.clone(),
+            transition: 0.,
+            transition_duration: 0.,
+            bounds
+        }
+    }
+    fn area_to(&self) -> &Hitbox {
+        match self.controllers.last() {
+            None | Some(CameraController::Follow) => self.region.map_or(&self.bounds, |region| &self.regions[region]),
+            //scripted shots can look anywhere on the map
+            _ => &self.bounds
+        }
+    }
+    //moves to the region holding the position, or back to the whole map outside them all, true when that pans the view
+    pub fn enter_region(&mut self, position: &Vector3) -> bool {
+        let region = self.regions.iter().position(|region| region.contains(position));
+        let placed = self.placed;
+        self.placed = true;
+        if region == self.region {
+            return false;
+        }
+        self.region = region;
+        if placed {
+            self.start_transition();
+        }
+        placed
+    }
+    fn start_transition(&mut self) {
+        self.area_from = self.area.clone();
+        self.transition = self.transition_duration;
+    }
+    pub fn push_controller(&mut self, controller: CameraController) {
+        self.controllers.push(controller);
+        self.start_transition();
+    }
+    pub fn pop_controller(&mut self) -> Option<CameraController> {
+        let controller = self.controllers.pop();
+        self.start_transition();
+        controller
+    }
+    //eases the runtime zoom over the given seconds, for cutscenes and the like
+    pub fn zoom_to(&mut self, zoom: f32, duration: f32) {
+        self.zoom_from = self.zoom;
+        self.zoom_target = zoom;
+        self.zoom_duration = duration;
+        self.zoom_elapsed = 0.;
+    }
+    fn advance_zoom(&mut self, dt: f32) {
+        self.zoom_elapsed += dt;
+        let t = if self.zoom_duration > 0. { (self.zoom_elapsed/self.zoom_duration).min(1.) } else { 1. };
+        let t = t*t*(3. - 2.*t);
+        self.zoom = self.zoom_from + (self.zoom_target - self.zoom_from)*t;
+    }
+}
+impl Default for CameraSettings {
+    fn default() -> CameraSettings {
+        CameraSettings::new(Vector3::new(0.,0.,0.), Hitbox {
+            position: Point3::new(0., 600., 0.),
+            size: Vector3::new(800., 600., 0.)
+        })
+    }
+}
+
+fn lerp_area(from: &Hitbox, to: &Hitbox, t: f32) -> Hitbox {
+    Hitbox {
+        position: from.position + (to.position - from.position)*t,
+        size: from.size + (to.size - from.size)*t
+    }
+}
+
+//range of camera centres that keeps the view inside the area, centred if the area is too small
+fn centre_limits(min: f32, length: f32, view: f32) -> (f32, f32) {
+    if length <= view {
+        let centre = min + length/2.;
+        (centre, centre)
+    } else {
+        (min + view/2., min + length - view/2.)
+    }
+}
+
+//decaying camera offset layered on top of the smoothed camera position
+#[derive(Default, Debug)]
+pub struct ScreenShake {
+    pub intensity: f32,
+    pub duration: f32,
+    pub remaining: f32
+}
+impl ScreenShake {
+    pub fn start(&mut self, intensity: f32, duration: f32) {
+        //never cut a stronger shake short
+        if self.remaining <= 0. || intensity >= self.intensity {
+            self.intensity = intensity;
+            self.duration = duration;
+            self.remaining = duration;
+        }
+    }
+    fn advance(&mut self, dt: f32) -> Vector3 {
+        self.remaining = (self.remaining - dt).max(0.);
+        if self.remaining <= 0. || self.duration <= 0. {
+            return Vector3::new(0., 0., 0.);
+        }
+        //cheap deterministic noise, fading out linearly
+        let (t, strength) = (self.remaining, self.intensity*self.remaining/self.duration);
+        Vector3::new((t*83.).sin()*strength, (t*97. + 1.3).sin()*strength, 0.)
+    }
+}
+
+//per-player camera state, the shared view frames all of these and each split view follows one
+#[derive(Debug)]
+pub struct CameraTarget {
+    //point the dead zone is centred on, only moves when the player pushes its edges
+    pub focus: Vector3,
+    pub look_ahead: f32,
+    //focus plus look-ahead
+    pub target: Vector3
+}
+impl CameraTarget {
+    pub fn new(position: Vector3) -> CameraTarget {
+        CameraTarget {
+            focus: position,
+            look_ahead: 0.,
+            target: position
+        }
+    }
+}
+impl Component for CameraTarget {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//camera for one half of the window in split-screen, following the player at index
+#[derive(Debug)]
+pub struct SplitView {
+    pub index: usize,
+    pub position: Vector3,
+    pub velocity: Vector3
+}
+impl SplitView {
+    pub fn new(index: usize, position: Vector3) -> SplitView {
+        SplitView {
+            index,
+            position,
+            velocity: Vector3::new(0., 0., 0.)
+        }
+    }
+}
+impl Component for SplitView {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//zoom that fits every target in a view of the given size with some margin, never closer than 1
+fn framing_zoom(targets: &[Vector3], view: (f32, f32), margin: f32) -> f32 {
+    if targets.len() < 2 {
+        return 1.;
+    }
+    let (mut min, mut max) = (targets[0], targets[0]);
+    for target in targets {
+        min = min.inf(target);
+        max = max.sup(target);
+    }
+    let span = max - min;
+    (view.0/(span.x + 2.*margin)).min(view.1/(span.y + 2.*margin)).min(1.)
+}
+
+//split once the players need more than the widest zoom, with a little slack before joining back up
+//so the views don't flicker at the threshold
+fn should_split(split: bool, needed: f32, min_zoom: f32) -> bool {
+    if split {
+        needed < min_zoom*1.1
+    } else {
+        needed < min_zoom
+    }
+}
+
+//springs position towards target and keeps a view of the given size inside area
+fn follow(position: &mut Vector3, velocity: &mut Vector3, target: &Vector3, area: &Hitbox, view: (f32, f32), camera_config: &CameraConfig, dt: f32) {
+    let mut offset = target - *position;
+    offset.z = 0.;
+    let acceleration = offset*camera_config.stiffness - *velocity*camera_config.damping;
+    *velocity += acceleration*dt;
+    *position += *velocity*dt;
+
+    let (min_x, max_x) = centre_limits(area.position.x, area.size.x, view.0);
+    let (min_y, max_y) = centre_limits(area.position.y - area.size.y, area.size.y, view.1);
+    //stop dead against the area edges instead of bouncing off them
+    if position.x < min_x || position.x > max_x {
+        position.x = position.x.min(max_x).max(min_x);
+        velocity.x = 0.;
+    }
+    if position.y < min_y || position.y > max_y {
+        position.y = position.y.min(max_y).max(min_y);
+        velocity.y = 0.;
+    }
+}
+
+pub struct UpdateCameraSystem;
+impl <'a> System<'a> for UpdateCameraSystem {
+    type SystemData = (
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Physics>,
+        WriteStorage<'a, CameraTarget>,
+        WriteStorage<'a, SplitView>,
+        WriteStorage<'a, Transform>,
+        Write<'a, CameraSettings>,
+        Write<'a, ScreenShake>,
+        Write<'a, HitStop>,
+        Read<'a, CameraConfig>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (cameras, players, physics_set, mut camera_targets, mut split_views, mut transform_set, mut camera_settings, mut screen_shake, mut hit_stop, camera_config, time): Self::SystemData) {
+        //a long frame would overshoot the spring
+        let dt = time.delta_seconds().min(0.05);
+        camera_settings.transition_duration = camera_config.region_transition;
+
+        //follow the interpolated sprites so the camera doesn't step at the physics rate
+        let (half_width, half_height) = (camera_config.dead_zone.0/2., camera_config.dead_zone.1/2.);
+        let mut positions = Vec::new();
+        let mut targets = Vec::new();
+        let mut player_targets = Vec::new();
+        for (player, physics, camera_target, transform) in (&players, &physics_set, &mut camera_targets, &transform_set).join() {
+            let position = *transform.translation();
+            let focus = &mut camera_target.focus;
+            focus.x = focus.x.max(position.x - half_width).min(position.x + half_width);
+            //with the vertical lock the camera only catches up on landing, or when the player leaves the dead zone
+            if physics.contacts.bottom || !camera_config.vertical_lock {
+                focus.y = position.y;
+            }
+            focus.y = focus.y.max(position.y - half_height).min(position.y + half_height);
+
+            //swing ahead of the player once they are actually moving
+            let look_ahead = if physics.velocity.x.abs() > 6. { physics.facing*camera_config.look_ahead } else { camera_target.look_ahead };
+            camera_target.look_ahead += (look_ahead - camera_target.look_ahead)*(camera_config.look_ahead_speed*dt).min(1.);
+            camera_target.target = Vector3::new(camera_target.focus.x + camera_target.look_ahead, camera_target.focus.y, 0.);
+            positions.push(position);
+            targets.push(camera_target.target);
+            player_targets.push((player.index, camera_target.target));
+        }
+
+        if !targets.is_empty() {
+            let count = targets.len() as f32;
+            let position = positions.iter().sum::<Vector3>()/count;
+            camera_settings.target = targets.iter().sum::<Vector3>()/count;
+
+            //hit-stop doubles as the freeze while the view pans between rooms
+            if camera_settings.enter_region(&position) && camera_config.freeze_transitions {
+                hit_stop.remaining = hit_stop.remaining.max(camera_config.region_transition);
+            }
+        }
+
+        //anything other than following the players overrides the target
+        let scripted = match camera_settings.controllers.last_mut() {
+            None | Some(CameraController::Follow) => None,
+            Some(CameraController::Fixed(point)) => Some(*point),
+            Some(CameraController::Focus(entity)) => transform_set.get(*entity).map(|transform| *transform.translation()),
+            Some(CameraController::Path(path)) => Some(path.advance(dt))
+        };
+        if let Some(target) = scripted {
+            camera_settings.target = Vector3::new(target.x, target.y, 0.);
+        }
+        if let Some(CameraController::Path(path)) = camera_settings.controllers.last() {
+            if path.finished() && path.pop_when_done {
+                camera_settings.pop_controller();
+            }
+        }
+
+        //zoom out until every player fits with some margin, splitting once even the widest zoom won't do
+        let zoom = camera_config.zoom*camera_settings.zoom;
+        let base = (camera_config.viewport.0/zoom, camera_config.viewport.1/zoom);
+        let needed = if scripted.is_none() { framing_zoom(&targets, base, camera_config.coop_margin) } else { 1. };
+        camera_settings.framing_zoom = needed.max(camera_config.coop_min_zoom);
+        camera_settings.split = camera_config.split_screen && should_split(camera_settings.split, needed, camera_config.coop_min_zoom);
+
+        camera_settings.transition = (camera_settings.transition - dt).max(0.);
+        camera_settings.area = if camera_settings.transition > 0. {
+            let t = 1. - camera_settings.transition/camera_settings.transition_duration;
+            lerp_area(&camera_settings.area_from, camera_settings.area_to(), t)
+        } else {
+            camera_settings.area_to().clone()
+        };
+
+        let shake = screen_shake.advance(dt);
+        let snap = |mut view: Vector3| {
+            if camera_config.pixel_perfect {
+                view.x = view.x.round();
+                view.y = view.y.round();
+            }
+            view
+        };
+        let (mut position, mut velocity) = (camera_settings.position, camera_settings.velocity);
+        follow(&mut position, &mut velocity, &camera_settings.target, &camera_settings.area, camera_settings.viewport, &camera_config, dt);
+        camera_settings.position = position;
+        camera_settings.velocity = velocity;
+        for (_, _, transform) in (&cameras, !&split_views, &mut transform_set).join() {
+            transform.set_translation(snap(position + shake));
+        }
+
+        //split views ride along with the shared one until the split, so they pull apart from where it was
+        let half = (camera_settings.viewport.0/2., camera_settings.viewport.1);
+        for (_, view, transform) in (&cameras, &mut split_views, &mut transform_set).join() {
+            if camera_settings.split {
+                let target = player_targets.iter()
+                    .find(|(index, _)| *index == view.index)
+                    .map_or(camera_settings.target, |(_, target)| *target);
+                follow(&mut view.position, &mut view.velocity, &target, &camera_settings.bounds, half, &camera_config, dt);
+            } else {
+                view.position = camera_settings.position;
+                view.velocity = camera_settings.velocity;
+            }
+            transform.set_translation(snap(view.position + shake));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BarSide {
+    Left,
+    Right,
+    Top,
+    Bottom
+}
+
+//black bar parented to the camera, sized by the viewport system
+#[derive(Debug)]
+pub struct LetterboxBar {
+    pub side: BarSide
+}
+impl Component for LetterboxBar {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//one plain black pixel stretched over each side of the view
+pub fn create_letterbox(world: &mut World, camera: Entity) {
+    let sprite_sheet = {
+        let loader = world.read_resource::<Loader>();
+        let texture = loader.load_from_data(
+            load_from_srgba(Srgba::new(0., 0., 0., 1.)).into(),
+            (),
+            &world.read_resource::<AssetStorage<Texture>>()
+        );
+        let sprite = Sprite::from_pixel_values(1, 1, 1, 1, 0, 0, [0.; 2], false, false);
+        loader.load_from_data(
+            SpriteSheet { texture, sprites: vec![sprite] },
+            (),
+            &world.read_resource::<AssetStorage<SpriteSheet>>()
+        )
+    };
+    for &side in &[BarSide::Left, BarSide::Right, BarSide::Top, BarSide::Bottom] {
+        world.create_entity()
+            .with(SpriteRender { sprite_sheet: sprite_sheet.clone(), sprite_number: 0 })
+            .with(Transform::default())
+            .with(Parent { entity: camera })
+            .with(LetterboxBar { side })
+            .build();
+    }
+}
+
+//fits the configured viewport into the window, at a whole number scale in pixel perfect mode
+#[derive(Default)]
+pub struct ViewportSystem {
+    projection: (f32, f32),
+    split: bool
+}
+impl <'a> System<'a> for ViewportSystem {
+    type SystemData = (
+        WriteStorage<'a, Camera>,
+        ReadStorage<'a, SplitView>,
+        ReadStorage<'a, LetterboxBar>,
+        WriteStorage<'a, Transform>,
+        Write<'a, CameraSettings>,
+        Read<'a, CameraConfig>,
+        ReadExpect<'a, ScreenDimensions>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (mut cameras, split_views, bars, mut transforms, mut camera_settings, camera_config, screen_dimensions, time): Self::SystemData) {
+        camera_settings.advance_zoom(time.delta_seconds());
+        //split views each hold a single player, so they drop the co-op zoom
+        let split = camera_settings.split;
+        let framing = if split { 1. } else { camera_settings.framing_zoom };
+        let zoom = camera_config.zoom*camera_settings.zoom*framing;
+        let visible = (camera_config.viewport.0/zoom, camera_config.viewport.1/zoom);
+        let window = (screen_dimensions.width(), screen_dimensions.height());
+        let mut scale = (window.0/visible.0).min(window.1/visible.1);
+        if camera_config.pixel_perfect && scale >= 1. {
+            scale = scale.floor();
+        }
+        //the projection always covers the whole window, bars hide what is outside the viewport
+        let projection = (window.0/scale, window.1/scale);
+        //the split views fill the window between them, so there are no bars
+        let letterbox = camera_config.letterbox && !split;
+        camera_settings.viewport = if letterbox { visible } else { projection };
+        if projection != self.projection || split != self.split {
+            self.projection = projection;
+            self.split = split;
+            let half = projection.0/2.;
+            for (camera, split_view) in (&mut cameras, split_views.maybe()).join() {
+                //the left view is drawn into the left half of the window, the right one over the whole window
+                //with its centre pushed onto the right half, see RenderSplitScreen
+                let (left, right) = match split_view.map(|view| view.index) {
+                    None => (-half, half),
+                    Some(0) => (-half/2., half/2.),
+                    Some(_) => (-half*1.5, half/2.)
+                };
+                camera.set_projection(Projection::orthographic(
+                    left, right, -projection.1/2., projection.1/2., 0.1, 2000.
+                ));
+            }
+        }
+
+        let (side_width, cap_height) = if letterbox {
+            ((projection.0 - visible.0).max(0.)/2., (projection.1 - visible.1).max(0.)/2.)
+        } else {
+            (0., 0.)
+        };
+        for (bar, transform) in (&bars, &mut transforms).join() {
+            let (x, y, width, height) = match bar.side {
+                BarSide::Left => (-(visible.0 + side_width)/2., 0., side_width, projection.1),
+                BarSide::Right => ((visible.0 + side_width)/2., 0., side_width, projection.1),
+                BarSide::Bottom => (0., -(visible.1 + cap_height)/2., visible.0, cap_height),
+                BarSide::Top => (0., (visible.1 + cap_height)/2., visible.0, cap_height)
+            };
+            //just in front of the camera so the bars cover everything in the scene
+            transform.set_translation_xyz(x, y, -1.);
+            transform.set_scale(Vector3::new(width, height, 1.));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitbox::testing::{hitbox};
+
+    fn settings() -> CameraSettings {
+        let mut camera_settings = CameraSettings::new(Vector3::new(0., 0., 0.), hitbox(0., 600., 800., 600.));
+        camera_settings.regions = vec![hitbox(0., 300., 200., 300.), hitbox(200., 300., 200., 300.)];
+        camera_settings
+    }
+
+    #[test]
+    fn starting_region_is_picked_up_without_a_pan() {
+        let mut camera_settings = settings();
+        assert!(!camera_settings.enter_region(&Vector3::new(50., 100., 0.)));
+        assert_eq!(camera_settings.region, Some(0));
+        assert_eq!(camera_settings.area_to(), &camera_settings.regions[0]);
+    }
+
+    #[test]
+    fn walking_into_another_region_pans() {
+        let mut camera_settings = settings();
+        camera_settings.enter_region(&Vector3::new(50., 100., 0.));
+        assert!(camera_settings.enter_region(&Vector3::new(250., 100., 0.)));
+        assert_eq!(camera_settings.region, Some(1));
+        //staying put is not another pan
+        assert!(!camera_settings.enter_region(&Vector3::new(260., 100., 0.)));
+    }
+
+    #[test]
+    fn leaving_every_region_falls_back_to_the_map() {
+        let mut camera_settings = settings();
+        camera_settings.enter_region(&Vector3::new(50., 100., 0.));
+        assert!(camera_settings.enter_region(&Vector3::new(600., 500., 0.)));
+        assert_eq!(camera_settings.region, None);
+        assert_eq!(camera_settings.area_to(), &camera_settings.bounds);
+    }
+
+    //two players side by side, a 400 pixel view with 48 pixels of margin each side
+    fn needed(distance: f32) -> f32 {
+        framing_zoom(&[Vector3::new(0., 0., 0.), Vector3::new(distance, 0., 0.)], (400., 300.), 48.)
+    }
+
+    #[test]
+    fn a_single_player_needs_no_zoom() {
+        assert_eq!(framing_zoom(&[Vector3::new(500., 20., 0.)], (400., 300.), 48.), 1.);
+        assert_eq!(needed(200.), 1.);
+    }
+
+    #[test]
+    fn splits_once_the_widest_zoom_is_not_enough() {
+        //0.6 zoom shows 400/0.6 = 666.7 pixels, the players plus margins fill that 570.7 pixels apart
+        assert!(!should_split(false, needed(560.), 0.6));
+        assert!(should_split(false, needed(580.), 0.6));
+    }
+
+    #[test]
+    fn joins_back_up_only_well_inside_the_limit() {
+        //back under the split distance but still within the slack, 400/0.66 - 96 = 510 pixels
+        assert!(should_split(true, needed(560.), 0.6));
+        assert!(should_split(true, needed(520.), 0.6));
+        assert!(!should_split(true, needed(500.), 0.6));
+    }
+}
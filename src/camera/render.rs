@@ -0,0 +1,169 @@
+use amethyst::{
+    ecs::{Join, Resources, SystemData, DispatcherBuilder},
+    ecs::prelude::{Entities, ReadStorage},
+    error::Error,
+    renderer::{
+        bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
+        pass::{DrawFlat2DDesc, DrawFlat2DTransparentDesc},
+        rendy::{
+            command::{QueueId, RenderPassEncoder},
+            factory::Factory,
+            graph::{
+                render::{PrepareResult, RenderGroup, RenderGroupDesc},
+                BufferAccess, GraphContext, ImageAccess, NodeBuffer, NodeImage
+            },
+            hal::{self, command::{AttachmentClear}, pso::{ClearRect, Rect}}
+        },
+        sprite_visibility::{SpriteVisibilitySortingSystem},
+        types::{Backend},
+        ActiveCamera, Camera
+    }
+};
+
+use super::{CameraSettings, SplitView};
+
+//draws the sprites like RenderFlat2D, through the shared camera or side by side through the split views
+//the flat pass only ever looks through the active camera, so each view swaps it in before its sprites are prepared
+//and the right view is drawn first over the whole window, then the left half is cleared and drawn over
+#[derive(Default, Debug)]
+pub struct RenderSplitScreen {
+    split: bool,
+    clear: [f32; 4]
+}
+impl RenderSplitScreen {
+    //colour the left half is cleared to, the same as the window's
+    pub fn with_clear(mut self, clear: [f32; 4]) -> Self {
+        self.clear = clear;
+        self
+    }
+}
+impl <B: Backend> RenderPlugin<B> for RenderSplitScreen {
+    fn on_build<'a, 'b>(&mut self, builder: &mut DispatcherBuilder<'a, 'b>) -> Result<(), Error> {
+        builder.add(SpriteVisibilitySortingSystem::new(), "sprite_visibility_system", &[]);
+        Ok(())
+    }
+    fn should_rebuild(&mut self, res: &Resources) -> bool {
+        let split = res.try_fetch::<CameraSettings>().map_or(false, |camera_settings| camera_settings.split);
+        let changed = split != self.split;
+        self.split = split;
+        changed
+    }
+    fn on_plan(&mut self, plan: &mut RenderPlan<B>, _factory: &mut Factory<B>, _res: &Resources) -> Result<(), Error> {
+        let (split, clear) = (self.split, self.clear);
+        plan.extend_target(Target::Main, move |ctx| {
+            if split {
+                ctx.add(RenderOrder::BeforeOpaque, ViewDesc { view: Some(1), clear: None }.builder())?;
+                ctx.add(RenderOrder::Opaque, DrawFlat2DDesc::new().builder())?;
+                ctx.add(RenderOrder::AfterOpaque, DrawFlat2DTransparentDesc::new().builder())?;
+                ctx.add(RenderOrder::BeforeTransparent, ViewDesc { view: Some(0), clear: Some(clear) }.builder())?;
+                ctx.add(RenderOrder::Transparent, LeftHalf(DrawFlat2DDesc::new()).builder())?;
+                ctx.add(RenderOrder::AfterTransparent, LeftHalf(DrawFlat2DTransparentDesc::new()).builder())?;
+            } else {
+                ctx.add(RenderOrder::BeforeOpaque, ViewDesc { view: None, clear: None }.builder())?;
+                ctx.add(RenderOrder::Opaque, DrawFlat2DDesc::new().builder())?;
+                ctx.add(RenderOrder::Transparent, DrawFlat2DTransparentDesc::new().builder())?;
+            }
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+//makes the split view at index, or the shared camera for None, the active one for the groups after it
+#[derive(Debug)]
+struct ViewDesc {
+    view: Option<usize>,
+    //clears the left half before that view is drawn over it
+    clear: Option<[f32; 4]>
+}
+impl <B: Backend> RenderGroupDesc<B, Resources> for ViewDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        _factory: &mut Factory<B>,
+        _queue: QueueId,
+        _res: &Resources,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>
+    ) -> Result<Box<dyn RenderGroup<B, Resources>>, failure::Error> {
+        Ok(Box::new(View {
+            view: self.view,
+            clear: self.clear,
+            size: (framebuffer_width, framebuffer_height)
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct View {
+    view: Option<usize>,
+    clear: Option<[f32; 4]>,
+    size: (u32, u32)
+}
+impl <B: Backend> RenderGroup<B, Resources> for View {
+    fn prepare(&mut self, _factory: &Factory<B>, _queue: QueueId, _index: usize, _subpass: hal::pass::Subpass<'_, B>, res: &Resources) -> PrepareResult {
+        let camera = {
+            let (entities, cameras, split_views) = <(Entities<'_>, ReadStorage<'_, Camera>, ReadStorage<'_, SplitView>)>::fetch(res);
+            (&entities, &cameras, split_views.maybe()).join()
+                .find(|(_, _, split_view)| split_view.map(|view| view.index) == self.view)
+                .map(|(entity, _, _)| entity)
+        };
+        if let Some(mut active_camera) = res.try_fetch_mut::<ActiveCamera>() {
+            active_camera.entity = camera;
+        }
+        PrepareResult::DrawRecord
+    }
+    fn draw_inline(&mut self, mut encoder: RenderPassEncoder<'_, B>, _index: usize, _subpass: hal::pass::Subpass<'_, B>, _res: &Resources) {
+        if let Some(colour) = self.clear {
+            let rect = ClearRect {
+                rect: Rect { x: 0, y: 0, w: (self.size.0/2) as i16, h: self.size.1 as i16 },
+                layers: 0..1
+            };
+            unsafe {
+                encoder.clear_attachments(
+                    &[
+                        AttachmentClear::Color { index: 0, value: colour.into() },
+                        AttachmentClear::DepthStencil { depth: Some(1.), stencil: None }
+                    ],
+                    &[rect]
+                );
+            }
+        }
+    }
+    fn dispose(self: Box<Self>, _factory: &mut Factory<B>, _res: &Resources) {}
+}
+
+//builds a group as if the framebuffer were half as wide, its viewport and scissor then cover just the left half
+#[derive(Debug)]
+struct LeftHalf<D>(D);
+impl <B: Backend, D: RenderGroupDesc<B, Resources>> RenderGroupDesc<B, Resources> for LeftHalf<D> {
+    fn buffers(&self) -> Vec<BufferAccess> {
+        self.0.buffers()
+    }
+    fn images(&self) -> Vec<ImageAccess> {
+        self.0.images()
+    }
+    fn depth(&self) -> bool {
+        self.0.depth()
+    }
+    fn colors(&self) -> usize {
+        self.0.colors()
+    }
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        queue: QueueId,
+        res: &Resources,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>
+    ) -> Result<Box<dyn RenderGroup<B, Resources>>, failure::Error> {
+        self.0.build(ctx, factory, queue, res, framebuffer_width/2, framebuffer_height, subpass, buffers, images)
+    }
+}
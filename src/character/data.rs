@@ -0,0 +1,121 @@
+use amethyst::{
+    assets::{Asset, AssetStorage, Handle},
+    core::{
+        math as na
+    },
+    ecs::{Component, VecStorage}
+};
+use serde::{Deserialize};
+
+use std::collections::HashMap;
+
+use crate::animation::{AnimationData, AnimationDefinition, AnimationType, LoopMode};
+use crate::combat::{AttackFrame};
+use crate::hitbox::{CollisionBox};
+
+use super::{CharacterType};
+
+type Vector3 = na::Vector3<f32>;
+
+//which character an entity is, the file stem of its data in assets/characters
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CharacterName(pub String);
+impl Component for CharacterName {
+    type Storage = VecStorage<Self>;
+}
+
+//offset is from the transform to the box center, like CollisionBox
+#[derive(Debug, Clone, Deserialize)]
+pub struct BoxDefinition {
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub offset: (f32, f32)
+}
+impl BoxDefinition {
+    pub fn collision_box(&self) -> CollisionBox {
+        CollisionBox::new(self.width, self.height, self.offset.0, self.offset.1)
+    }
+}
+
+//the same area on each of the given frames of a swing, offsets assume facing right
+#[derive(Debug, Clone, Deserialize)]
+pub struct AttackDefinition {
+    pub frames: Vec<usize>,
+    pub area: BoxDefinition,
+    pub damage: u32,
+    pub knockback: (f32, f32)
+}
+
+//everything about one character, loaded from assets/characters/<name>.ron with its art in <name>_sprite_sheet
+//animations, boxes and attacks are keyed like AnimationType::keys
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterData {
+    //the team it fights for and the physics it moves with
+    pub side: CharacterType,
+    pub health: u32,
+    //seconds of invulnerability after each hit
+    pub invulnerability: f32,
+    //pushes other bodies out instead of overlapping them
+    #[serde(default)]
+    pub solid: bool,
+    //swings from the moment it spawns
+    #[serde(default)]
+    pub always_attacking: bool,
+    pub animations: HashMap<String, AnimationDefinition>,
+    pub collision_boxes: HashMap<String, BoxDefinition>,
+    #[serde(default)]
+    pub attacks: HashMap<String, Vec<AttackDefinition>>
+}
+impl Asset for CharacterData {
+    const NAME: &'static str = "platformer::CharacterData";
+    type Data = Self;
+    type HandleStorage = VecStorage<Handle<Self>>;
+}
+
+//tries the exact key, then the key without its variant
+fn lookup<'a, T>(table: &'a HashMap<String, T>, anim_type: &AnimationType) -> Option<&'a T> {
+    let (key, fallback) = anim_type.keys();
+    table.get(&key).or_else(|| table.get(fallback))
+}
+
+#[derive(Default, Debug)]
+pub struct CharacterResource {
+    //keyed by character name
+    sets: HashMap<String, Handle<CharacterData>>
+}
+impl CharacterResource {
+    pub fn insert(&mut self, name: String, handle: Handle<CharacterData>) {
+        self.sets.insert(name, handle);
+    }
+    pub fn get<'a>(&self, storage: &'a AssetStorage<CharacterData>, name: &CharacterName) -> Option<&'a CharacterData> {
+        self.sets.get(&name.0).and_then(|handle| storage.get(handle))
+    }
+    //anything missing falls back to idle
+    pub fn animation(&self, storage: &AssetStorage<CharacterData>, name: &CharacterName, anim_type: &AnimationType) -> AnimationData {
+        let definition = self.get(storage, name)
+            .and_then(|data| lookup(&data.animations, anim_type).or_else(|| data.animations.get("idle")));
+        match definition {
+            Some(definition) => AnimationData::new(definition.frames.clone(), definition.frame_duration, anim_type.clone(), definition.mode == LoopMode::Loop),
+            //missing or not loaded yet, hold the first frame
+            None => AnimationData::new(vec![0], 1., anim_type.clone(), true)
+        }
+    }
+    //boxes are sized to the character art, feet line up with the bottom of the sprite
+    pub fn collision_box(&self, storage: &AssetStorage<CharacterData>, name: &CharacterName, anim_type: &AnimationType) -> CollisionBox {
+        self.get(storage, name)
+            .and_then(|data| lookup(&data.collision_boxes, anim_type).or_else(|| data.collision_boxes.get("idle")))
+            .map_or_else(CollisionBox::default, |definition| definition.collision_box())
+    }
+    //damaging area live on the given frame of an attack animation, if any
+    pub fn attack_frame(&self, storage: &AssetStorage<CharacterData>, name: &CharacterName, anim_type: &AnimationType, frame: usize) -> Option<AttackFrame> {
+        self.get(storage, name)
+            .and_then(|data| lookup(&data.attacks, anim_type))
+            .and_then(|attacks| attacks.iter().find(|attack| attack.frames.contains(&frame)))
+            .map(|attack| AttackFrame {
+                area: attack.area.collision_box(),
+                damage: attack.damage,
+                knockback: Vector3::new(attack.knockback.0, attack.knockback.1, 0.)
+            })
+    }
+}
@@ -7,9 +7,18 @@ use amethyst::{
     ecs::prelude::{Read, ReadStorage, WriteStorage}
 };
 
+use serde::{Serialize, Deserialize};
+
 use crate::system::{Physics};
 use crate::animation::{SpriteAnimation, AnimationType};
+use crate::combat::{Frozen};
+use crate::tuning::{JumpTuning};
+
+mod data;
+
+pub use self::data::{CharacterName, CharacterData, CharacterResource};
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CharacterType {
     Player,
     Enemy
@@ -25,16 +34,77 @@ impl Default for CharacterType {
 
 #[derive(Default, Debug)]
 pub struct Player {
+    //which co-op player this is, picks the input bindings
+    pub index: usize,
     pub is_attacking: bool,
     pub attack_combo: u8,
-    pub attack_timer: f32
+    pub attack_timer: f32,
+    pub jump_held: bool,
+    pub jump_buffer: f32,
+    pub jump_spent: bool,
+    pub wall_sliding: bool,
+    pub wall_jump_lock: f32,
+    pub air_jumps_used: u32,
+    //set when an air jump starts, cleared once the animation picks it up
+    pub air_jumped: bool,
+    pub dash_held: bool,
+    pub dash_timer: f32,
+    pub dash_cooldown: f32,
+    pub dash_spent: bool,
+    pub crouching: bool,
+    pub sliding: bool,
+    pub slide_time: f32,
+    pub climbing: bool
 }
 impl Player {
-    pub fn new() -> Player {
+    pub fn new(index: usize) -> Player {
         Player {
+            index,
             is_attacking: false,
             attack_combo: 0,
-            attack_timer: 0.0
+            attack_timer: 0.0,
+            jump_held: false,
+            jump_buffer: 0.0,
+            jump_spent: false,
+            wall_sliding: false,
+            wall_jump_lock: 0.0,
+            air_jumps_used: 0,
+            air_jumped: false,
+            dash_held: false,
+            dash_timer: 0.0,
+            dash_cooldown: 0.0,
+            dash_spent: false,
+            crouching: false,
+            sliding: false,
+            slide_time: 0.0,
+            climbing: false
+        }
+    }
+    pub fn is_dashing(&self) -> bool {
+        self.dash_timer > 0.
+    }
+    //remember presses for a short while so early presses still count, true once jump is let go
+    pub fn buffer_jump(&mut self, jump: bool, tuning: &JumpTuning, dt: f32) -> bool {
+        let (pressed, released) = (jump && !self.jump_held, !jump && self.jump_held);
+        self.jump_held = jump;
+        self.jump_buffer = if pressed {
+            tuning.buffer_time
+        } else {
+            (self.jump_buffer - dt).max(0.)
+        };
+        released
+    }
+    //coyote time lets a jump through shortly after walking off a ledge, jump_spent stops
+    //one press firing twice so a jump buffered before landing goes off on the first step down
+    pub fn can_jump(&self, airborne_time: f32, tuning: &JumpTuning) -> bool {
+        !self.jump_spent && airborne_time <= tuning.coyote_time
+    }
+    //input binding name for this player, the first player keeps the plain names
+    pub fn binding(&self, name: &str) -> String {
+        if self.index == 0 {
+            name.to_string()
+        } else {
+            format!("{}_{}", name, self.index + 1)
         }
     }
 }
@@ -42,6 +112,16 @@ impl Component for Player {
     type Storage = VecStorage<Self>;
 }
 
+//movement abilities unlocked so far, flip these to gate progression
+#[derive(Default, Debug)]
+pub struct Abilities {
+    pub air_jump: bool,
+    pub dash: bool
+}
+impl Component for Abilities {
+    type Storage = VecStorage<Self>;
+}
+
 pub struct PlayerSystem;
 impl <'a> System<'a> for PlayerSystem {
     type SystemData = (
@@ -49,11 +129,12 @@ impl <'a> System<'a> for PlayerSystem {
         WriteStorage<'a, Player>,
         WriteStorage<'a, AnimationType>,
         ReadStorage<'a, SpriteAnimation>,
+        ReadStorage<'a, Frozen>,
         Read<'a, Time>,
     );
-    fn run(&mut self, (physics_set, mut players, mut anim_types, animations, time): Self::SystemData) {
-        let dt = time.delta_seconds();
-        for (physics, player, anim_type, anim) in (&physics_set, &mut players, &mut anim_types, &animations).join() {
+    fn run(&mut self, (physics_set, mut players, mut anim_types, animations, frozen_set, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        for (physics, player, anim_type, anim, _) in (&physics_set, &mut players, &mut anim_types, &animations, !&frozen_set).join() {
             let mut new_anim_type = anim_type.clone();
             match *anim_type {
                 AnimationType::Idle => {
@@ -61,9 +142,15 @@ impl <'a> System<'a> for PlayerSystem {
                         let new_combo = (player.attack_combo+1)%3;
                         new_anim_type = AnimationType::Attack(new_combo);
                         player.attack_combo = new_combo;
+                    } else if player.is_dashing() {
+                        new_anim_type = AnimationType::Dash;
+                    } else if player.climbing {
+                        new_anim_type = AnimationType::Climb;
+                    } else if player.crouching {
+                        new_anim_type = AnimationType::Crouch;
                     } else if physics.is_jumping {
                         new_anim_type = AnimationType::Jump(false, false);
-                    } else if physics.velocity.x.abs() > 0.1 {
+                    } else if physics.velocity.x.abs() > 6. {
                         new_anim_type = AnimationType::Run;
                     }
                 },
@@ -79,15 +166,31 @@ impl <'a> System<'a> for PlayerSystem {
                         let new_combo = (player.attack_combo+1)%3;
                         new_anim_type = AnimationType::Attack(new_combo);
                         player.attack_combo = new_combo;
+                    } else if player.is_dashing() {
+                        new_anim_type = AnimationType::Dash;
+                    } else if player.climbing {
+                        new_anim_type = AnimationType::Climb;
+                    } else if player.sliding {
+                        new_anim_type = AnimationType::Slide;
+                    } else if player.crouching {
+                        new_anim_type = AnimationType::Crouch;
                     } else if physics.is_jumping {
                         new_anim_type = AnimationType::Jump(false, true);
-                    } else if physics.velocity.x.abs() < 0.1 {
+                    } else if physics.velocity.x.abs() < 6. {
                         new_anim_type = AnimationType::Idle;
                     }
                 },
                 AnimationType::Jump(falling, running) => {
-                    if !physics.is_jumping {
+                    if player.climbing {
+                        new_anim_type = AnimationType::Climb;
+                    } else if !physics.is_jumping {
                         new_anim_type = AnimationType::Idle;
+                    } else if player.wall_sliding {
+                        new_anim_type = AnimationType::WallSlide;
+                    } else if player.is_dashing() {
+                        new_anim_type = AnimationType::Dash;
+                    } else if player.air_jumped {
+                        new_anim_type = AnimationType::AirJump;
                     } else if !falling && physics.velocity.y < 0. {
                         new_anim_type = AnimationType::Jump(true, running);
                     } else if player.is_attacking {
@@ -95,7 +198,73 @@ impl <'a> System<'a> for PlayerSystem {
                         new_anim_type = AnimationType::Attack(new_combo);
                         player.attack_combo = new_combo;
                     }
-                }
+                },
+                AnimationType::WallSlide => {
+                    if player.climbing {
+                        new_anim_type = AnimationType::Climb;
+                    } else if !physics.is_jumping {
+                        new_anim_type = AnimationType::Idle;
+                    } else if player.is_dashing() {
+                        new_anim_type = AnimationType::Dash;
+                    } else if player.air_jumped {
+                        new_anim_type = AnimationType::AirJump;
+                    } else if !player.wall_sliding {
+                        new_anim_type = AnimationType::Jump(physics.velocity.y < 0., false);
+                    }
+                },
+                AnimationType::AirJump => {
+                    player.air_jumped = false;
+                    if player.climbing {
+                        new_anim_type = AnimationType::Climb;
+                    } else if !physics.is_jumping {
+                        new_anim_type = AnimationType::Idle;
+                    } else if player.is_dashing() {
+                        new_anim_type = AnimationType::Dash;
+                    } else if player.wall_sliding {
+                        new_anim_type = AnimationType::WallSlide;
+                    } else if anim.animation_type == AnimationType::AirJump && anim.finished {
+                        new_anim_type = AnimationType::Jump(true, false);
+                    }
+                },
+                AnimationType::Dash => {
+                    if !player.is_dashing() {
+                        new_anim_type = if physics.is_jumping {
+                            AnimationType::Jump(true, false)
+                        } else {
+                            AnimationType::Idle
+                        };
+                    }
+                },
+                AnimationType::Crouch => {
+                    if player.sliding {
+                        new_anim_type = AnimationType::Slide;
+                    } else if !player.crouching {
+                        new_anim_type = AnimationType::Idle;
+                    }
+                },
+                AnimationType::Slide => {
+                    if physics.is_jumping {
+                        new_anim_type = AnimationType::Jump(true, false);
+                    } else if player.crouching {
+                        new_anim_type = AnimationType::Crouch;
+                    } else if !player.sliding {
+                        new_anim_type = AnimationType::Idle;
+                    }
+                },
+                AnimationType::Climb => {
+                    if !player.climbing {
+                        new_anim_type = if physics.is_jumping {
+                            AnimationType::Jump(physics.velocity.y < 0., false)
+                        } else {
+                            AnimationType::Idle
+                        };
+                    }
+                },
+                //recovery and death are driven by the health system
+                AnimationType::Hurt => {
+                    player.is_attacking = false;
+                },
+                AnimationType::Die => {}
             }
             //apply only if changed
             if *anim_type != new_anim_type {
@@ -111,3 +280,49 @@ impl <'a> System<'a> for PlayerSystem {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1./60.;
+
+    #[test]
+    fn an_early_press_is_remembered_for_the_buffer_time() {
+        let tuning = JumpTuning::default();
+        let mut player = Player::new(0);
+        player.buffer_jump(true, &tuning, DT);
+        assert_eq!(player.jump_buffer, tuning.buffer_time);
+
+        //holding the button doesn't refresh the press, it runs out after 0.15s either way
+        for _ in 0..8 {
+            player.buffer_jump(true, &tuning, DT);
+        }
+        assert!(player.jump_buffer > 0.);
+        player.buffer_jump(true, &tuning, DT);
+        player.buffer_jump(true, &tuning, DT);
+        assert_eq!(player.jump_buffer, 0.);
+    }
+
+    #[test]
+    fn letting_go_is_reported_once() {
+        let tuning = JumpTuning::default();
+        let mut player = Player::new(0);
+        assert!(!player.buffer_jump(true, &tuning, DT));
+        assert!(player.buffer_jump(false, &tuning, DT));
+        assert!(!player.buffer_jump(false, &tuning, DT));
+    }
+
+    #[test]
+    fn jumps_are_allowed_just_after_leaving_a_ledge() {
+        let tuning = JumpTuning::default();
+        let mut player = Player::new(0);
+        assert!(player.can_jump(0., &tuning));
+        assert!(player.can_jump(tuning.coyote_time, &tuning));
+        assert!(!player.can_jump(tuning.coyote_time + DT, &tuning));
+
+        //a jump already taken can't be taken again inside the window
+        player.jump_spent = true;
+        assert!(!player.can_jump(0., &tuning));
+    }
+}
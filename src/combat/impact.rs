@@ -0,0 +1,110 @@
+use amethyst::{
+    core::{
+        timing::{Time}
+    },
+    ecs::{Component, System, SystemData, Resources, Join, DenseVecStorage},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    shrev::{EventChannel, ReaderId}
+};
+use serde::{Serialize, Deserialize};
+
+use crate::camera::{ScreenShake};
+use super::{DamageEvent, Health};
+
+//loaded from config/impact.ron
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactConfig {
+    //seconds the game holds still when a hit lands
+    pub hit_stop: f32,
+    //freeze the whole simulation instead of just the attacker and target
+    pub global_hit_stop: bool,
+    //maximum camera offset in pixels
+    pub shake_intensity: f32,
+    pub shake_duration: f32
+}
+impl Default for ImpactConfig {
+    fn default() -> ImpactConfig {
+        ImpactConfig {
+            hit_stop: 0.06,
+            global_hit_stop: false,
+            shake_intensity: 3.,
+            shake_duration: 0.2
+        }
+    }
+}
+
+//global freeze, while active the fixed timestep systems are not dispatched
+#[derive(Default, Debug)]
+pub struct HitStop {
+    pub remaining: f32
+}
+impl HitStop {
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.
+    }
+}
+
+//per-entity freeze, movement, physics and animation skip entities that have one
+#[derive(Debug)]
+pub struct Frozen {
+    pub remaining: f32
+}
+impl Component for Frozen {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//must run before the health system so it sees targets as they were before the hit
+#[derive(Default)]
+pub struct ImpactSystem {
+    damage_reader: Option<ReaderId<DamageEvent>>
+}
+impl <'a> System<'a> for ImpactSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, Frozen>,
+        Write<'a, HitStop>,
+        Write<'a, ScreenShake>,
+        Read<'a, ImpactConfig>,
+        Read<'a, EventChannel<DamageEvent>>,
+        Read<'a, Time>,
+    );
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.damage_reader = Some(res.fetch_mut::<EventChannel<DamageEvent>>().register_reader());
+    }
+    fn run(&mut self, (entities, health_set, mut frozen_set, mut hit_stop, mut screen_shake, config, damage_events, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        let mut thawed = Vec::new();
+        for (entity, frozen) in (&entities, &mut frozen_set).join() {
+            frozen.remaining -= dt;
+            if frozen.remaining <= 0. {
+                thawed.push(entity);
+            }
+        }
+        for entity in thawed {
+            frozen_set.remove(entity);
+        }
+        for event in damage_events.read(self.damage_reader.as_mut().unwrap()) {
+            //only attacks that actually land get feedback
+            let attacker = match event.source {
+                Some(attacker) => attacker,
+                None => continue
+            };
+            match health_set.get(event.target) {
+                Some(health) if !health.is_dead() && health.invulnerable_timer <= 0. => {},
+                _ => continue
+            }
+            if config.global_hit_stop {
+                hit_stop.remaining = hit_stop.remaining.max(config.hit_stop);
+            } else {
+                frozen_set.insert(event.target, Frozen { remaining: config.hit_stop }).ok();
+                //a thrower can be anywhere by the time the projectile lands
+                if event.melee {
+                    frozen_set.insert(attacker, Frozen { remaining: config.hit_stop }).ok();
+                }
+            }
+            screen_shake.start(config.shake_intensity, config.shake_duration);
+        }
+    }
+}
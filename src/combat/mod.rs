@@ -0,0 +1,238 @@
+use amethyst::{
+    assets::{AssetStorage},
+    core::{
+        math as na,
+        timing::{Time},
+        Hidden
+    },
+    ecs::{Component, System, SystemData, Resources, Join, DenseVecStorage, Entity},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    shrev::{EventChannel, ReaderId}
+};
+
+mod impact;
+mod projectile;
+
+pub use self::impact::{ImpactConfig, ImpactSystem, HitStop, Frozen};
+pub use self::projectile::{
+    ProjectileType, ProjectileRequest, Projectile, ProjectilePool, ProjectileResource, ProjectileSystem,
+    RangedAttack, RangedAttackSystem
+};
+
+use crate::animation::{SpriteAnimation, AnimationType};
+use crate::character::{CharacterType, CharacterName, CharacterData, CharacterResource, Player};
+use crate::event::{GameEvent};
+use crate::hitbox::{CollisionBox};
+use crate::system::{Physics};
+
+type Vector3 = na::Vector3<f32>;
+
+//damaging area that is live on one frame of an attack animation, offsets assume facing right
+#[derive(Clone, Debug)]
+pub struct AttackFrame {
+    pub area: CollisionBox,
+    pub damage: u32,
+    pub knockback: Vector3
+}
+
+//any source of harm, melee swings publish one per target per swing
+#[derive(Debug, Clone)]
+pub struct DamageEvent {
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub amount: u32,
+    pub knockback: Vector3,
+    //kills regardless of health and invulnerability
+    pub lethal: bool,
+    //landed up close, the attacker shares the hit-stop
+    pub melee: bool
+}
+
+//tracks who a swing has already connected with
+#[derive(Default, Debug)]
+pub struct MeleeAttack {
+    targets_hit: Vec<Entity>,
+    animation_type: AnimationType,
+    last_frame: usize
+}
+impl Component for MeleeAttack {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub struct MeleeSystem;
+impl <'a> System<'a> for MeleeSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CharacterType>,
+        ReadStorage<'a, CharacterName>,
+        ReadStorage<'a, SpriteAnimation>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Physics>,
+        WriteStorage<'a, MeleeAttack>,
+        Read<'a, CharacterResource>,
+        Read<'a, AssetStorage<CharacterData>>,
+        Write<'a, EventChannel<DamageEvent>>,
+        Write<'a, EventChannel<GameEvent>>,
+    );
+    fn run(&mut self, (entities, character_types, character_names, animations, collision_boxes, physics_set, mut melee_attacks, character_resource, character_data, mut damage_events, mut game_events): Self::SystemData) {
+        for (attacker, char_type, name, anim, physics, melee) in (&entities, &character_types, &character_names, &animations, &physics_set, &mut melee_attacks).join() {
+            let frame_index = anim.frame_index();
+            //a new animation or a looped one starts a new swing
+            if melee.animation_type != anim.animation_type || frame_index < melee.last_frame {
+                melee.targets_hit.clear();
+                melee.animation_type = anim.animation_type.clone();
+                if let AnimationType::Attack(_) = anim.animation_type {
+                    game_events.single_write(GameEvent::Attacked { entity: attacker });
+                }
+            }
+            melee.last_frame = frame_index;
+
+            let attack_frame = match character_resource.attack_frame(&character_data, name, &anim.animation_type, frame_index) {
+                Some(attack_frame) => attack_frame,
+                None => continue
+            };
+            //mirror the attack area to the side the attacker is facing
+            let mut area = attack_frame.area.clone();
+            area.offset.x *= physics.facing;
+            let attack_hb = area.hitbox_at(&physics.position);
+
+            for (target, target_type, collision_box, target_physics) in (&entities, &character_types, &collision_boxes, &physics_set).join() {
+                if target == attacker || target_type == char_type || melee.targets_hit.contains(&target) {
+                    continue;
+                }
+                if attack_hb.collides_with(&collision_box.hitbox_at(&target_physics.position)) {
+                    melee.targets_hit.push(target);
+                    damage_events.single_write(DamageEvent {
+                        source: Some(attacker),
+                        target,
+                        amount: attack_frame.damage,
+                        knockback: Vector3::new(attack_frame.knockback.x*physics.facing, attack_frame.knockback.y, 0.),
+                        lethal: false,
+                        melee: true
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+    //seconds of invulnerability granted after each hit
+    pub invulnerability: f32,
+    pub invulnerable_timer: f32,
+    resume_animation: Option<AnimationType>
+}
+impl Health {
+    pub fn new(max: u32, invulnerability: f32) -> Health {
+        Health {
+            current: max,
+            max,
+            invulnerability,
+            invulnerable_timer: 0.,
+            resume_animation: None
+        }
+    }
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+impl Component for Health {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//where a character comes back after dying, characters without one are removed
+#[derive(Debug)]
+pub struct Respawn {
+    pub position: Vector3
+}
+impl Component for Respawn {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[derive(Default)]
+pub struct HealthSystem {
+    damage_reader: Option<ReaderId<DamageEvent>>
+}
+impl <'a> System<'a> for HealthSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, SpriteAnimation>,
+        ReadStorage<'a, Respawn>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Physics>,
+        WriteStorage<'a, AnimationType>,
+        WriteStorage<'a, Hidden>,
+        Read<'a, EventChannel<DamageEvent>>,
+        Write<'a, EventChannel<GameEvent>>,
+        Read<'a, Time>,
+    );
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.damage_reader = Some(res.fetch_mut::<EventChannel<DamageEvent>>().register_reader());
+    }
+    fn run(&mut self, (entities, players, animations, respawns, mut health_set, mut physics_set, mut anim_types, mut hidden_set, damage_events, mut game_events, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        for event in damage_events.read(self.damage_reader.as_mut().unwrap()) {
+            let health = match health_set.get_mut(event.target) {
+                Some(health) => health,
+                None => continue
+            };
+            if health.is_dead() || (health.invulnerable_timer > 0. && !event.lethal) {
+                continue;
+            }
+            health.current = if event.lethal { 0 } else { health.current.saturating_sub(event.amount) };
+            health.invulnerable_timer = health.invulnerability;
+            game_events.single_write(GameEvent::Damaged { entity: event.target, source: event.source, amount: event.amount });
+            if health.is_dead() {
+                game_events.single_write(GameEvent::Died { entity: event.target });
+            }
+            if let Some(physics) = physics_set.get_mut(event.target) {
+                physics.apply_impulse(event.knockback);
+            }
+            if let Some(anim_type) = anim_types.get_mut(event.target) {
+                if health.is_dead() {
+                    *anim_type = AnimationType::Die;
+                } else {
+                    //players go back to their state machine, others pick up where they left off
+                    if *anim_type != AnimationType::Hurt {
+                        health.resume_animation = Some(if players.contains(event.target) {
+                            AnimationType::Idle
+                        } else {
+                            anim_type.clone()
+                        });
+                    }
+                    *anim_type = AnimationType::Hurt;
+                }
+            }
+        }
+
+        for (entity, health, anim, anim_type, physics) in (&entities, &mut health_set, &animations, &mut anim_types, &mut physics_set).join() {
+            //blink while invulnerable
+            health.invulnerable_timer = (health.invulnerable_timer - dt).max(0.);
+            if health.invulnerable_timer > 0. && !health.is_dead() && (health.invulnerable_timer*10.) as u32 % 2 == 0 {
+                hidden_set.insert(entity, Hidden).ok();
+            } else {
+                hidden_set.remove(entity);
+            }
+
+            if *anim_type == AnimationType::Hurt && anim.animation_type == AnimationType::Hurt && anim.finished {
+                *anim_type = health.resume_animation.take().unwrap_or(AnimationType::Idle);
+            }
+
+            if health.is_dead() && anim.animation_type == AnimationType::Die && anim.finished {
+                if let Some(respawn) = respawns.get(entity) {
+                    physics.teleport(respawn.position);
+                    health.current = health.max;
+                    health.invulnerable_timer = health.invulnerability;
+                    *anim_type = AnimationType::Idle;
+                } else {
+                    entities.delete(entity).ok();
+                }
+            }
+        }
+    }
+}
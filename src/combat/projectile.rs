@@ -0,0 +1,309 @@
+use amethyst::{
+    assets::{Handle},
+    core::{
+        math as na,
+        timing::{Time},
+        transform::{Transform},
+        Hidden
+    },
+    ecs::{Component, System, SystemData, Resources, Join, DenseVecStorage, Entity},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    input::{InputHandler, StringBindings},
+    renderer::{SpriteRender, SpriteSheet, Transparent},
+    shrev::{EventChannel, ReaderId}
+};
+
+use crate::character::{CharacterType, Player};
+use crate::environment::{Platform};
+use crate::hitbox::{Hitbox, CollisionBox};
+use crate::system::{Physics};
+use crate::tilemap::{TileMap};
+use crate::tuning::{PhysicsConfig};
+use super::{DamageEvent, Health};
+
+type Point3 = na::Point3<f32>;
+type Vector3 = na::Vector3<f32>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectileType {
+    Knife,
+    Rock
+}
+
+//frames index into projectile_sprite_sheet, gravity is a fraction of the shared tuning
+#[derive(Debug, Clone)]
+pub struct ProjectileData {
+    pub frames: Vec<usize>,
+    pub time_per_frame: f32,
+    pub size: Vector3,
+    pub speed: f32,
+    pub gravity_scale: f32,
+    pub lifetime: f32,
+    pub damage: u32,
+    pub knockback: Vector3
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct ProjectileResource;
+impl ProjectileResource {
+    pub fn data(&self, projectile_type: ProjectileType) -> ProjectileData {
+        match projectile_type {
+            ProjectileType::Knife => ProjectileData {
+                frames: vec![0,1],
+                time_per_frame: 1./12.,
+                size: Vector3::new(8., 4., 0.),
+                speed: 260.,
+                gravity_scale: 0.15,
+                lifetime: 1.5,
+                damage: 1,
+                knockback: Vector3::new(120., 60., 0.)
+            },
+            ProjectileType::Rock => ProjectileData {
+                frames: vec![2,3],
+                time_per_frame: 1./8.,
+                size: Vector3::new(6., 6., 0.),
+                speed: 160.,
+                gravity_scale: 0.6,
+                lifetime: 2.,
+                damage: 1,
+                knockback: Vector3::new(100., 80., 0.)
+            }
+        }
+    }
+}
+
+//asks the projectile system to launch one, velocity is taken as given
+#[derive(Debug, Clone)]
+pub struct ProjectileRequest {
+    pub projectile_type: ProjectileType,
+    pub owner: Option<Entity>,
+    pub position: Vector3,
+    pub velocity: Vector3
+}
+
+#[derive(Debug)]
+pub struct Projectile {
+    pub projectile_type: ProjectileType,
+    pub owner: Option<Entity>,
+    //projectiles pass through characters of the owner's type
+    pub owner_type: Option<CharacterType>,
+    //center of the projectile
+    pub position: Vector3,
+    pub previous_position: Vector3,
+    pub velocity: Vector3,
+    pub gravity_scale: f32,
+    pub lifetime: f32,
+    pub age: f32,
+    pub active: bool
+}
+impl Projectile {
+    pub fn hitbox(&self, size: &Vector3) -> Hitbox {
+        Hitbox {
+            position: Point3::new(self.position.x - size.x/2., self.position.y + size.y/2., 0.),
+            size: *size
+        }
+    }
+}
+impl Component for Projectile {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//spent projectiles are hidden and handed out again instead of being deleted
+#[derive(Default, Debug)]
+pub struct ProjectilePool {
+    pub sprite_sheet: Option<Handle<SpriteSheet>>,
+    free: Vec<Entity>
+}
+impl ProjectilePool {
+    pub fn new(sprite_sheet: Handle<SpriteSheet>) -> ProjectilePool {
+        ProjectilePool {
+            sprite_sheet: Some(sprite_sheet),
+            free: Vec::new()
+        }
+    }
+}
+
+//fires while the throw button is held, or at players in range for everyone else
+#[derive(Debug)]
+pub struct RangedAttack {
+    pub projectile_type: ProjectileType,
+    pub cooldown: f32,
+    pub range: f32,
+    timer: f32
+}
+impl RangedAttack {
+    pub fn new(projectile_type: ProjectileType, cooldown: f32, range: f32) -> RangedAttack {
+        RangedAttack {
+            projectile_type,
+            cooldown,
+            range,
+            timer: 0.
+        }
+    }
+}
+impl Component for RangedAttack {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub struct RangedAttackSystem;
+impl <'a> System<'a> for RangedAttackSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Physics>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, RangedAttack>,
+        Read<'a, ProjectileResource>,
+        Read<'a, InputHandler<StringBindings>>,
+        Write<'a, EventChannel<ProjectileRequest>>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (entities, players, collision_boxes, physics_set, health_set, mut ranged_attacks, projectile_resource, input, mut requests, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        let player_positions: Vec<Vector3> = (&players, &physics_set).join()
+            .map(|(_, physics)| physics.position)
+            .collect();
+        for (entity, collision_box, physics, health, ranged) in (&entities, &collision_boxes, &physics_set, health_set.maybe(), &mut ranged_attacks).join() {
+            ranged.timer = (ranged.timer - dt).max(0.);
+            if ranged.timer > 0. || health.map_or(false, |health| health.is_dead()) {
+                continue;
+            }
+            let direction = if let Some(player) = players.get(entity) {
+                if !input.action_is_down(&player.binding("throw")).unwrap_or(false) {
+                    continue;
+                }
+                physics.facing
+            } else {
+                match player_positions.iter().find(|position| (*position - physics.position).norm() <= ranged.range) {
+                    Some(position) => if position.x < physics.position.x { -1. } else { 1. },
+                    None => continue
+                }
+            };
+            ranged.timer = ranged.cooldown;
+            let speed = projectile_resource.data(ranged.projectile_type).speed;
+            requests.single_write(ProjectileRequest {
+                projectile_type: ranged.projectile_type,
+                owner: Some(entity),
+                position: physics.position + Vector3::new(collision_box.offset.x + direction*collision_box.size.x/2., collision_box.offset.y, 0.),
+                velocity: Vector3::new(direction*speed, 0., 0.)
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ProjectileSystem {
+    request_reader: Option<ReaderId<ProjectileRequest>>
+}
+impl <'a> System<'a> for ProjectileSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CharacterType>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Physics>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Platform>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, SpriteRender>,
+        WriteStorage<'a, Transparent>,
+        WriteStorage<'a, Hidden>,
+        Write<'a, ProjectilePool>,
+        Read<'a, ProjectileResource>,
+        Read<'a, TileMap>,
+        Read<'a, PhysicsConfig>,
+        Read<'a, EventChannel<ProjectileRequest>>,
+        Write<'a, EventChannel<DamageEvent>>,
+        Read<'a, Time>,
+    );
+    fn setup(&mut self, res: &mut Resources) {
+        Self::SystemData::setup(res);
+        self.request_reader = Some(res.fetch_mut::<EventChannel<ProjectileRequest>>().register_reader());
+    }
+    fn run(&mut self, (entities, character_types, collision_boxes, physics_set, health_set, platforms, mut projectiles, mut transforms, mut sprite_renders, mut transparents, mut hidden_set, mut pool, projectile_resource, tilemap, physics_config, requests, mut damage_events, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        for request in requests.read(self.request_reader.as_mut().unwrap()) {
+            let sprite_sheet = match &pool.sprite_sheet {
+                Some(sprite_sheet) => sprite_sheet.clone(),
+                None => continue
+            };
+            let data = projectile_resource.data(request.projectile_type);
+            let projectile = Projectile {
+                projectile_type: request.projectile_type,
+                owner: request.owner,
+                owner_type: request.owner.and_then(|owner| character_types.get(owner).cloned()),
+                position: request.position,
+                previous_position: request.position,
+                velocity: request.velocity,
+                gravity_scale: data.gravity_scale,
+                lifetime: data.lifetime,
+                age: 0.,
+                active: true
+            };
+            //reuse a spent projectile if there is one, only grow the pool when all are in flight
+            let entity = pool.free.pop().unwrap_or_else(|| entities.create());
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(request.position.x, request.position.y, 0.5);
+            if request.velocity.x < 0. {
+                transform.set_rotation_y_axis(std::f32::consts::PI);
+            }
+            projectiles.insert(entity, projectile).ok();
+            transforms.insert(entity, transform).ok();
+            sprite_renders.insert(entity, SpriteRender { sprite_sheet, sprite_number: data.frames[0] }).ok();
+            transparents.insert(entity, Transparent).ok();
+            hidden_set.remove(entity);
+        }
+
+        let platform_hitboxes: Vec<(Entity, Hitbox)> = (&entities, &platforms).join()
+            .map(|(entity, platform)| (entity, platform.hitbox()))
+            .collect();
+        let mut spent = Vec::new();
+        for (entity, projectile, sprite_render) in (&entities, &mut projectiles, &mut sprite_renders).join() {
+            if !projectile.active {
+                continue;
+            }
+            let data = projectile_resource.data(projectile.projectile_type);
+            projectile.previous_position = projectile.position;
+            projectile.age += dt;
+            projectile.velocity.y += physics_config.tuning.gravity*projectile.gravity_scale*dt;
+            let frame = (projectile.age/data.time_per_frame) as usize % data.frames.len();
+            sprite_render.sprite_number = data.frames[frame];
+
+            //tiles and platforms stop the projectile where it hits
+            let sweep = projectile.hitbox(&data.size).sweep(&(projectile.velocity*dt), &tilemap, &platform_hitboxes);
+            projectile.position += sweep.displacement;
+            let mut hit = sweep.contacts.normal().norm() > 0. || projectile.age >= projectile.lifetime;
+
+            let hb = projectile.hitbox(&data.size);
+            for (target, target_type, collision_box, physics, health) in (&entities, &character_types, &collision_boxes, &physics_set, &health_set).join() {
+                if hit {
+                    break;
+                }
+                if Some(target) == projectile.owner || Some(target_type) == projectile.owner_type.as_ref() || health.is_dead() {
+                    continue;
+                }
+                if hb.collides_with(&collision_box.hitbox_at(&physics.position)) {
+                    let direction = if projectile.velocity.x < 0. { -1. } else { 1. };
+                    damage_events.single_write(DamageEvent {
+                        source: projectile.owner,
+                        target,
+                        amount: data.damage,
+                        knockback: Vector3::new(direction*data.knockback.x, data.knockback.y, 0.),
+                        lethal: false,
+                        melee: false
+                    });
+                    hit = true;
+                }
+            }
+            if hit {
+                projectile.active = false;
+                spent.push(entity);
+            }
+        }
+        for entity in spent {
+            hidden_set.insert(entity, Hidden).ok();
+            pool.free.push(entity);
+        }
+    }
+}
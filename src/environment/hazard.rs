@@ -0,0 +1,83 @@
+use amethyst::{
+    core::{
+        math as na
+    },
+    ecs::{System, Join},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    shrev::{EventChannel}
+};
+
+use crate::combat::{DamageEvent, Health, Respawn};
+use crate::hitbox::{Hitbox, CollisionBox};
+use crate::system::{Physics};
+use crate::tilemap::{TileMap, TileProperty};
+use crate::tuning::{PhysicsConfig};
+
+type Vector3 = na::Vector3<f32>;
+
+//checkpoint rectangles from the map's object groups
+#[derive(Default, Debug)]
+pub struct Checkpoints {
+    pub regions: Vec<Hitbox>
+}
+impl Checkpoints {
+    pub fn from_map(tilemap: &TileMap) -> Checkpoints {
+        Checkpoints {
+            regions: tilemap.objects("checkpoint").into_iter()
+                .map(|object| tilemap.object_hitbox(object))
+                .collect()
+        }
+    }
+}
+
+//spikes hurt, lava and falling out of the map kill outright
+pub struct HazardSystem;
+impl <'a> System<'a> for HazardSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Physics>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, Respawn>,
+        Read<'a, TileMap>,
+        Read<'a, Checkpoints>,
+        Read<'a, PhysicsConfig>,
+        Write<'a, EventChannel<DamageEvent>>,
+    );
+    fn run(&mut self, (entities, collision_boxes, physics_set, health_set, mut respawns, tilemap, checkpoints, physics_config, mut damage_events): Self::SystemData) {
+        let hazard_tuning = &physics_config.hazards;
+        for (entity, collision_box, physics, health, respawn) in (&entities, &collision_boxes, &physics_set, &health_set, (&mut respawns).maybe()).join() {
+            if health.is_dead() {
+                continue;
+            }
+            let hb = collision_box.hitbox_at(&physics.position);
+            //touching a checkpoint moves where the character comes back
+            if let Some(respawn) = respawn {
+                if let Some(checkpoint) = checkpoints.regions.iter().find(|checkpoint| checkpoint.collides_with(&hb)) {
+                    respawn.position = Vector3::new(checkpoint.position.x + checkpoint.size.x/2., checkpoint.position.y - checkpoint.size.y/2., 0.);
+                }
+            }
+
+            if physics.position.y < -hazard_tuning.kill_plane_depth || tilemap.has_property_in(TileProperty::Lava, &hb) {
+                damage_events.single_write(DamageEvent {
+                    source: None,
+                    target: entity,
+                    amount: health.current,
+                    knockback: Vector3::new(0., 0., 0.),
+                    lethal: true,
+                    melee: false
+                });
+            } else if tilemap.has_property_in(TileProperty::Spikes, &hb) {
+                //bounce back the way the character came
+                damage_events.single_write(DamageEvent {
+                    source: None,
+                    target: entity,
+                    amount: hazard_tuning.spike_damage,
+                    knockback: Vector3::new(-physics.facing*hazard_tuning.spike_knockback.0, hazard_tuning.spike_knockback.1, 0.),
+                    lethal: false,
+                    melee: false
+                });
+            }
+        }
+    }
+}
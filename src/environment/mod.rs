@@ -0,0 +1,138 @@
+use amethyst::{
+    core::{
+        math as na,
+        timing::{Time}
+    },
+    ecs::{Component, System, Join, DenseVecStorage, Entity},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    shrev::{EventChannel}
+};
+
+mod hazard;
+mod platform;
+mod trigger;
+
+pub use self::hazard::{Checkpoints, HazardSystem};
+pub use self::platform::{Platform, PlatformMotion, PlatformSystem, create_platforms};
+pub use self::trigger::{Trigger, TriggerSystem, create_triggers};
+
+use crate::combat::{DamageEvent};
+use crate::hitbox::{Hitbox, CollisionBox};
+use crate::system::{Physics};
+use crate::tilemap::{TileMap, TileProperty};
+use crate::tuning::{PhysicsConfig};
+
+type Vector3 = na::Vector3<f32>;
+
+//water rectangles from the map's object groups, on top of any water tile layer
+#[derive(Default, Debug)]
+pub struct WaterVolumes {
+    pub regions: Vec<Hitbox>
+}
+impl WaterVolumes {
+    pub fn from_map(tilemap: &TileMap) -> WaterVolumes {
+        WaterVolumes {
+            regions: tilemap.objects("water").into_iter()
+                .map(|object| tilemap.object_hitbox(object))
+                .collect()
+        }
+    }
+    pub fn contains(&self, tilemap: &TileMap, point: &Vector3) -> bool {
+        tilemap.has_property_at(TileProperty::Water, point.x, point.y) ||
+            self.regions.iter().any(|region| region.contains(point))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaterEventKind {
+    Entered,
+    Exited
+}
+
+//published when a body crosses the surface, for splashes and sounds
+#[derive(Debug, Clone)]
+pub struct WaterEvent {
+    pub entity: Entity,
+    pub kind: WaterEventKind,
+    pub position: Vector3
+}
+
+//lets a character drown, only drains while its head is under water
+#[derive(Debug)]
+pub struct Breath {
+    pub capacity: f32,
+    pub remaining: f32,
+    drown_timer: f32
+}
+impl Breath {
+    pub fn new(capacity: f32) -> Breath {
+        Breath {
+            capacity,
+            remaining: capacity,
+            drown_timer: 0.
+        }
+    }
+}
+impl Component for Breath {
+    type Storage = DenseVecStorage<Self>;
+}
+
+pub struct WaterSystem;
+impl <'a> System<'a> for WaterSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CollisionBox>,
+        WriteStorage<'a, Physics>,
+        WriteStorage<'a, Breath>,
+        Read<'a, TileMap>,
+        Read<'a, WaterVolumes>,
+        Read<'a, PhysicsConfig>,
+        Write<'a, EventChannel<WaterEvent>>,
+        Write<'a, EventChannel<DamageEvent>>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (entities, collision_boxes, mut physics_set, mut breath_set, tilemap, water_volumes, physics_config, mut water_events, mut damage_events, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        let water_tuning = &physics_config.water;
+        for (entity, collision_box, physics, breath) in (&entities, &collision_boxes, &mut physics_set, breath_set.maybe()).join() {
+            //swimming once the middle of the body is wet
+            let center = physics.position + collision_box.offset;
+            let in_water = water_volumes.contains(&tilemap, &center);
+            if in_water != physics.in_water {
+                water_events.single_write(WaterEvent {
+                    entity,
+                    kind: if in_water { WaterEventKind::Entered } else { WaterEventKind::Exited },
+                    position: center
+                });
+                physics.in_water = in_water;
+            }
+
+            let breath = match breath {
+                Some(breath) => breath,
+                None => continue
+            };
+            let head = center + Vector3::new(0., collision_box.size.y/2., 0.);
+            if water_volumes.contains(&tilemap, &head) {
+                breath.remaining = (breath.remaining - dt).max(0.);
+            } else {
+                breath.remaining = breath.capacity;
+                breath.drown_timer = 0.;
+            }
+            //out of air hurts at a steady rate
+            if breath.remaining <= 0. {
+                breath.drown_timer -= dt;
+                if breath.drown_timer <= 0. {
+                    breath.drown_timer = water_tuning.drown_interval;
+                    damage_events.single_write(DamageEvent {
+                        source: None,
+                        target: entity,
+                        amount: water_tuning.drown_damage,
+                        knockback: Vector3::new(0., 0., 0.),
+                        lethal: false,
+                        melee: false
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use amethyst::{
+    core::{
+        math as na,
+        timing::{Time},
+        transform::{Transform},
+        Parent
+    },
+    ecs::{Component, System, Join, DenseVecStorage},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    prelude::*,
+    shrev::{EventChannel}
+};
+
+use crate::event::{GameEvent};
+use crate::hitbox::{Hitbox, CollisionBox, push_out};
+use crate::system::{Physics};
+use crate::tilemap::{TileMap, MapObjectData};
+
+type Point3 = na::Point3<f32>;
+type Vector3 = na::Vector3<f32>;
+
+#[derive(Debug, Clone)]
+pub enum PlatformMotion {
+    //back and forth through the points at a constant speed
+    Waypoints { points: Vec<Vector3>, speed: f32 },
+    //oscillates around where the platform was placed
+    Sine { amplitude: Vector3, period: f32 },
+    Still
+}
+
+//kinematic platform, solid from every side and carries whatever stands on it
+#[derive(Debug)]
+pub struct Platform {
+    pub motion: PlatformMotion,
+    pub size: Vector3,
+    //top-left corner, the same as a hitbox
+    pub position: Vector3,
+    pub previous_position: Vector3,
+    origin: Vector3,
+    time: f32,
+    target: usize,
+    step: i32
+}
+impl Platform {
+    pub fn new(position: Vector3, size: Vector3, motion: PlatformMotion) -> Platform {
+        //repeated points would make zero-length segments the platform can never move along
+        let motion = match motion {
+            PlatformMotion::Waypoints { mut points, speed } => {
+                points.dedup();
+                if points.len() < 2 { PlatformMotion::Still } else { PlatformMotion::Waypoints { points, speed } }
+            },
+            PlatformMotion::Sine { period, .. } if period <= 0. => PlatformMotion::Still,
+            motion => motion
+        };
+        Platform {
+            motion,
+            size,
+            position,
+            previous_position: position,
+            origin: position,
+            time: 0.,
+            target: 0,
+            step: 1
+        }
+    }
+    pub fn hitbox(&self) -> Hitbox {
+        Hitbox {
+            position: Point3::new(self.position.x, self.position.y, 0.),
+            size: self.size
+        }
+    }
+    pub fn delta(&self) -> Vector3 {
+        self.position - self.previous_position
+    }
+    fn advance(&mut self, dt: f32) {
+        self.previous_position = self.position;
+        self.time += dt;
+        match &self.motion {
+            PlatformMotion::Waypoints { points, speed } => {
+                if points.len() < 2 {
+                    return;
+                }
+                //spend the whole step's distance, turning around at either end
+                let mut distance = speed*dt;
+                while distance > 0. {
+                    let to_target = points[self.target] - self.position;
+                    if to_target.norm() > distance {
+                        self.position += to_target.normalize()*distance;
+                        break;
+                    }
+                    self.position = points[self.target];
+                    distance -= to_target.norm();
+                    let next = self.target as i32 + self.step;
+                    if next < 0 || next >= points.len() as i32 {
+                        self.step = -self.step;
+                    }
+                    self.target = (self.target as i32 + self.step) as usize;
+                }
+            },
+            PlatformMotion::Sine { amplitude, period } => {
+                let phase = self.time/period*2.*std::f32::consts::PI;
+                self.position = self.origin + amplitude*phase.sin();
+            },
+            PlatformMotion::Still => {}
+        }
+    }
+}
+impl Component for Platform {
+    type Storage = DenseVecStorage<Self>;
+}
+
+fn number(object: &MapObjectData, name: &str) -> Option<f32> {
+    object.property(name).and_then(|value| value.as_f64()).map(|value| value as f32)
+}
+
+//platform objects name a polyline in "path", or give sine motion with "amplitude_x", "amplitude_y" and "period"
+pub fn create_platforms(world: &mut World, tilemap: &TileMap) {
+    let (tile_width, tile_height) = tilemap.tile_size();
+    for object in tilemap.objects("platform") {
+        let hitbox = tilemap.object_hitbox(object);
+        let position = Vector3::new(hitbox.position.x, hitbox.position.y, 0.);
+        let path = object.property("path")
+            .and_then(|value| value.as_str())
+            .and_then(|name| tilemap.object_named(name));
+        let motion = if let Some(path) = path {
+            PlatformMotion::Waypoints {
+                points: tilemap.object_path(path),
+                speed: number(object, "speed").unwrap_or(40.)
+            }
+        } else if let Some(period) = number(object, "period") {
+            if period <= 0. {
+                log::warn!("platform {} has a period of {}, it stays still until that is positive", object.id, period);
+            }
+            PlatformMotion::Sine {
+                amplitude: Vector3::new(number(object, "amplitude_x").unwrap_or(0.), number(object, "amplitude_y").unwrap_or(0.), 0.),
+                period
+            }
+        } else {
+            PlatformMotion::Still
+        };
+        let mut transform = Transform::default();
+        transform.set_translation(position);
+        let platform = world.create_entity()
+            .with(Platform::new(position, hitbox.size, motion))
+            .with(transform)
+            .build();
+
+        //a row of the "tile" property's tile, placed relative to the box the same way the map's tiles are
+        if let Some(tile) = object.property("tile").and_then(|value| value.as_u64()) {
+            let columns = (hitbox.size.x/tile_width).round().max(1.) as usize;
+            for column in 0..columns {
+                let mut tile_transform = Transform::default();
+                tile_transform.set_translation_xyz(column as f32*tile_width, -tile_height, 0.);
+                world.create_entity()
+                    .with(tilemap.tile_sprite(tile as usize))
+                    .with(tile_transform)
+                    .with(Parent { entity: platform })
+                    .build();
+            }
+        }
+    }
+}
+
+//moves platforms after physics, carrying riders along and shoving anything else out of the way
+pub struct PlatformSystem;
+impl <'a> System<'a> for PlatformSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CollisionBox>,
+        WriteStorage<'a, Platform>,
+        WriteStorage<'a, Physics>,
+        Write<'a, EventChannel<GameEvent>>,
+        Read<'a, TileMap>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (entities, collision_boxes, mut platforms, mut physics_set, mut game_events, tilemap, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        for platform in (&mut platforms).join() {
+            platform.advance(dt);
+        }
+        for (entity, collision_box, physics) in (&entities, &collision_boxes, &mut physics_set).join() {
+            //riders move with the platform, but never into a wall
+            if let Some(platform) = physics.platform.and_then(|entity| platforms.get(entity)) {
+                let sweep = collision_box.hitbox_at(&physics.position).sweep(&platform.delta(), &tilemap, &[]);
+                physics.position += sweep.displacement;
+            }
+            for platform in (&platforms).join() {
+                if let Some(penetration) = collision_box.hitbox_at(&physics.position).penetration(&platform.hitbox()) {
+                    //a platform rising into a falling body catches it
+                    if push_out(physics, penetration) {
+                        game_events.single_write(GameEvent::Landed { entity });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoints(points: &[(f32, f32)], speed: f32) -> Platform {
+        let points: Vec<Vector3> = points.iter().map(|&(x, y)| Vector3::new(x, y, 0.)).collect();
+        Platform::new(points[0], Vector3::new(16., 8., 0.), PlatformMotion::Waypoints { points, speed })
+    }
+
+    #[test]
+    fn turns_around_at_the_end_of_the_path() {
+        let mut platform = waypoints(&[(0., 0.), (10., 0.)], 10.);
+        platform.advance(0.5);
+        assert_eq!(platform.position, Vector3::new(5., 0., 0.));
+        //the rest of the step after reaching the end is spent heading back
+        platform.advance(1.);
+        assert_eq!(platform.position, Vector3::new(5., 0., 0.));
+        platform.advance(0.5);
+        assert_eq!(platform.position, Vector3::new(0., 0., 0.));
+        assert_eq!(platform.delta(), Vector3::new(-5., 0., 0.));
+    }
+
+    #[test]
+    fn repeated_points_do_not_stall_the_path() {
+        let mut platform = waypoints(&[(0., 0.), (0., 0.), (10., 0.), (10., 0.)], 10.);
+        platform.advance(0.5);
+        assert_eq!(platform.position, Vector3::new(5., 0., 0.));
+        platform.advance(1.);
+        assert_eq!(platform.position, Vector3::new(5., 0., 0.));
+
+        //nothing left to move between
+        let platform = waypoints(&[(3., 3.), (3., 3.)], 10.);
+        assert!(match platform.motion { PlatformMotion::Still => true, _ => false });
+    }
+
+    #[test]
+    fn sine_motion_peaks_a_quarter_period_in() {
+        let motion = PlatformMotion::Sine { amplitude: Vector3::new(0., 8., 0.), period: 4. };
+        let mut platform = Platform::new(Vector3::new(0., 100., 0.), Vector3::new(16., 8., 0.), motion);
+        platform.advance(1.);
+        assert!((platform.position.y - 108.).abs() < 0.001);
+        platform.advance(2.);
+        assert!((platform.position.y - 92.).abs() < 0.001);
+    }
+
+    #[test]
+    fn a_period_of_zero_stays_still() {
+        let motion = PlatformMotion::Sine { amplitude: Vector3::new(0., 8., 0.), period: 0. };
+        let mut platform = Platform::new(Vector3::new(0., 100., 0.), Vector3::new(16., 8., 0.), motion);
+        platform.advance(1.);
+        assert_eq!(platform.position, Vector3::new(0., 100., 0.));
+    }
+}
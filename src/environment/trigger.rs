@@ -0,0 +1,73 @@
+use amethyst::{
+    ecs::{Component, System, Join, DenseVecStorage, Entity},
+    ecs::prelude::{Entities, Write, ReadStorage, WriteStorage},
+    prelude::*,
+    shrev::{EventChannel}
+};
+
+use crate::character::{CharacterType};
+use crate::event::{GameEvent};
+use crate::hitbox::{Hitbox, CollisionBox};
+use crate::system::{Physics};
+use crate::tilemap::{TileMap};
+
+//map rectangle that reports characters going in and out, action says what it is for
+#[derive(Debug)]
+pub struct Trigger {
+    pub area: Hitbox,
+    pub action: String,
+    occupants: Vec<Entity>
+}
+impl Trigger {
+    pub fn new(area: Hitbox, action: String) -> Trigger {
+        Trigger {
+            area,
+            action,
+            occupants: Vec::new()
+        }
+    }
+}
+impl Component for Trigger {
+    type Storage = DenseVecStorage<Self>;
+}
+
+//trigger objects take their action from an "action" property, falling back to the object's name
+pub fn create_triggers(world: &mut World, tilemap: &TileMap) {
+    for object in tilemap.objects("trigger") {
+        let action = object.property("action")
+            .and_then(|value| value.as_str())
+            .unwrap_or(&object.name)
+            .to_string();
+        world.create_entity()
+            .with(Trigger::new(tilemap.object_hitbox(object), action))
+            .build();
+    }
+}
+
+pub struct TriggerSystem;
+impl <'a> System<'a> for TriggerSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CharacterType>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Physics>,
+        WriteStorage<'a, Trigger>,
+        Write<'a, EventChannel<GameEvent>>,
+    );
+    fn run(&mut self, (entities, character_types, collision_boxes, physics_set, mut triggers, mut game_events): Self::SystemData) {
+        for (trigger_entity, trigger) in (&entities, &mut triggers).join() {
+            let inside: Vec<Entity> = (&entities, &character_types, &collision_boxes, &physics_set).join()
+                .filter(|(_, _, collision_box, physics)| trigger.area.collides_with(&collision_box.hitbox_at(&physics.position)))
+                .map(|(entity, _, _, _)| entity)
+                .collect();
+            for &entity in inside.iter().filter(|entity| !trigger.occupants.contains(entity)) {
+                game_events.single_write(GameEvent::EnteredTrigger { entity, trigger: trigger_entity, action: trigger.action.clone() });
+            }
+            //deleted characters count as leaving
+            for &entity in trigger.occupants.iter().filter(|entity| !inside.contains(entity)) {
+                game_events.single_write(GameEvent::ExitedTrigger { entity, trigger: trigger_entity, action: trigger.action.clone() });
+            }
+            trigger.occupants = inside;
+        }
+    }
+}
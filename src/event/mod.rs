@@ -0,0 +1,16 @@
+use amethyst::{
+    ecs::{Entity}
+};
+
+//gameplay happenings published on an EventChannel<GameEvent>, so audio, camera, ui and
+//scripts can react without reaching into each other's components
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    Landed { entity: Entity },
+    Jumped { entity: Entity },
+    Attacked { entity: Entity },
+    Damaged { entity: Entity, source: Option<Entity>, amount: u32 },
+    Died { entity: Entity },
+    EnteredTrigger { entity: Entity, trigger: Entity, action: String },
+    ExitedTrigger { entity: Entity, trigger: Entity, action: String }
+}
@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Hitbox;
+
+//uniform grid broadphase, boxes are bucketed by the cells they cover
+//so only boxes sharing a cell are handed to the narrow phase
+#[derive(Debug)]
+pub struct UniformGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>
+}
+impl UniformGrid {
+    pub fn new(cell_size: f32) -> UniformGrid {
+        UniformGrid {
+            cell_size,
+            cells: HashMap::new()
+        }
+    }
+    pub fn clear(&mut self) {
+        //keep the buckets around so their allocations get reused next step
+        for ids in self.cells.values_mut() {
+            ids.clear();
+        }
+    }
+    pub fn insert(&mut self, id: usize, hitbox: &Hitbox) {
+        let (col_lo, col_hi) = (
+            (hitbox.position.x/self.cell_size).floor() as i32,
+            ((hitbox.position.x + hitbox.size.x)/self.cell_size).floor() as i32
+        );
+        let (row_lo, row_hi) = (
+            ((hitbox.position.y - hitbox.size.y)/self.cell_size).floor() as i32,
+            (hitbox.position.y/self.cell_size).floor() as i32
+        );
+        for col in col_lo..=col_hi {
+            for row in row_lo..=row_hi {
+                self.cells.entry((col, row)).or_insert_with(Vec::new).push(id);
+            }
+        }
+    }
+    //every pair of ids sharing at least one cell, each pair reported once in a stable order
+    pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = HashSet::new();
+        for ids in self.cells.values() {
+            for (n, &a) in ids.iter().enumerate() {
+                for &b in ids[n+1..].iter().filter(|&&b| b != a) {
+                    pairs.insert((a.min(b), a.max(b)));
+                }
+            }
+        }
+        let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+        pairs.sort();
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitbox::testing::{hitbox};
+
+    #[test]
+    fn finds_pairs_across_a_cell_boundary() {
+        let mut grid = UniformGrid::new(64.);
+        //the first box straddles the boundary at x 64, the second lies wholly past it
+        grid.insert(0, &hitbox(56., 30., 12., 20.));
+        grid.insert(1, &hitbox(66., 30., 12., 20.));
+        grid.insert(2, &hitbox(200., 30., 12., 20.));
+        assert_eq!(grid.candidate_pairs(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn reports_each_pair_once() {
+        let mut grid = UniformGrid::new(64.);
+        //both boxes cover the same four cells
+        grid.insert(0, &hitbox(60., 70., 10., 10.));
+        grid.insert(1, &hitbox(58., 68., 10., 10.));
+        assert_eq!(grid.candidate_pairs(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn never_pairs_a_box_with_itself() {
+        let mut grid = UniformGrid::new(64.);
+        grid.insert(0, &hitbox(60., 70., 10., 10.));
+        assert!(grid.candidate_pairs().is_empty());
+        //inserting the same id again, say after a missed clear, still makes no self-pair
+        grid.insert(0, &hitbox(60., 70., 10., 10.));
+        assert!(grid.candidate_pairs().is_empty());
+    }
+
+    #[test]
+    fn clear_forgets_every_box() {
+        let mut grid = UniformGrid::new(64.);
+        grid.insert(0, &hitbox(10., 30., 12., 20.));
+        grid.insert(1, &hitbox(12., 30., 12., 20.));
+        grid.clear();
+        assert!(grid.candidate_pairs().is_empty());
+    }
+}
@@ -1,69 +1,437 @@
 
 use amethyst::{
+    assets::{AssetStorage},
     core::{
         math as na,
-    }
+    },
+    ecs::{Component, System, Join, DenseVecStorage, NullStorage, Entity},
+    ecs::prelude::{Entities, Read, Write, ReadStorage, WriteStorage},
+    shrev::{EventChannel}
 };
 
+mod broadphase;
+
+use crate::animation::{AnimationType};
+use crate::character::{CharacterName, CharacterData, CharacterResource};
+use crate::event::{GameEvent};
+use crate::system::{Physics};
 use crate::tilemap::TileMap;
 
+use self::broadphase::{UniformGrid};
+
 type Point3 = na::Point3<f32>;
 type Vector3 = na::Vector3<f32>;
 
+//tolerance for treating a box edge as touching a tile edge
+const EPSILON: f32 = 0.01;
+
+//collision box relative to an entity's transform, offset is from the transform to the box center
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollisionBox {
+    pub size: Vector3,
+    pub offset: Vector3
+}
+impl CollisionBox {
+    pub fn new(width: f32, height: f32, offset_x: f32, offset_y: f32) -> CollisionBox {
+        CollisionBox {
+            size: Vector3::new(width, height, 0.),
+            offset: Vector3::new(offset_x, offset_y, 0.)
+        }
+    }
+    pub fn hitbox_at(&self, translation: &Vector3) -> Hitbox {
+        //hitbox position is the top-left corner of the box
+        Hitbox {
+            position: Point3::new(
+                translation.x + self.offset.x - self.size.x/2.,
+                translation.y + self.offset.y + self.size.y/2.,
+                0.
+            ),
+            size: self.size
+        }
+    }
+}
+impl Component for CollisionBox {
+    type Storage = DenseVecStorage<Self>;
+}
+impl Default for CollisionBox {
+    fn default() -> CollisionBox {
+        CollisionBox::new(12., 10., 0., 0.)
+    }
+}
+
+//keep each collision box in sync with the character's current animation state
+pub struct CollisionBoxSystem;
+impl <'a> System<'a> for CollisionBoxSystem {
+    type SystemData = (
+        ReadStorage<'a, CharacterName>,
+        ReadStorage<'a, AnimationType>,
+        WriteStorage<'a, CollisionBox>,
+        Read<'a, CharacterResource>,
+        Read<'a, AssetStorage<CharacterData>>,
+    );
+    fn run(&mut self, (character_names, anim_types, mut collision_boxes, character_resource, character_data): Self::SystemData) {
+        for (name, anim_type, collision_box) in (&character_names, &anim_types, &mut collision_boxes).join() {
+            let new_collision_box = character_resource.collision_box(&character_data, name, anim_type);
+            //apply only if changed
+            if *collision_box != new_collision_box {
+                *collision_box = new_collision_box;
+            }
+        }
+    }
+}
+
+//which sides of a box are touching solid tiles
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Contacts {
+    pub left: bool,
+    pub right: bool,
+    pub top: bool,
+    pub bottom: bool
+}
+impl Contacts {
+    //combined surface normal of every side in contact
+    pub fn normal(&self) -> Vector3 {
+        let mut normal = Vector3::new(0., 0., 0.);
+        if self.left { normal.x += 1.; }
+        if self.right { normal.x -= 1.; }
+        if self.bottom { normal.y += 1.; }
+        if self.top { normal.y -= 1.; }
+        normal
+    }
+}
+
 #[derive(Debug)]
+pub struct Sweep {
+    pub displacement: Vector3,
+    pub contacts: Contacts,
+    //platform the box came to rest on, if any
+    pub ground: Option<Entity>
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Hitbox {
     pub position: Point3,
     pub size: Vector3
 }
 impl Hitbox {
-    /* pub fn collides_with(&self, other: &Hitbox) -> bool {
+    pub fn collides_with(&self, other: &Hitbox) -> bool {
         self.position.x < other.position.x+other.size.x &&
         self.position.x+self.size.x > other.position.x &&
-        self.position.y < other.position.y+other.size.y &&
-        self.position.y+self.size.y > other.position.y
-    } */
-    pub fn colliding_with_ceiling(&self, map: &TileMap) -> bool {
-        let num_iter_x = (self.size.x/map.tile_map_data.tilewidth as f32).ceil() as usize;
-        for x in 0..num_iter_x {
-            let pix_x = self.position.x + (x as f32)*map.tile_map_data.tilewidth as f32;
-            let pix_y = self.position.y + self.size.y + 4.;
-            if map.is_tile_collidable(map.xy_to_i(map.pix_to_map(pix_x), map.pix_to_map(pix_y))) {
-                return true;
+        self.position.y-self.size.y < other.position.y &&
+        self.position.y > other.position.y-other.size.y
+    }
+    pub fn contains(&self, point: &Vector3) -> bool {
+        point.x >= self.position.x && point.x <= self.position.x+self.size.x &&
+        point.y <= self.position.y && point.y >= self.position.y-self.size.y
+    }
+    //smallest translation that moves this box out of the other one
+    pub fn penetration(&self, other: &Hitbox) -> Option<Vector3> {
+        if !self.collides_with(other) {
+            return None;
+        }
+        let overlap_x = (self.position.x+self.size.x).min(other.position.x+other.size.x) - self.position.x.max(other.position.x);
+        let overlap_y = self.position.y.min(other.position.y) - (self.position.y-self.size.y).max(other.position.y-other.size.y);
+        if overlap_x < overlap_y {
+            let left_of_other = self.position.x+self.size.x/2. < other.position.x+other.size.x/2.;
+            Some(Vector3::new(if left_of_other { -overlap_x } else { overlap_x }, 0., 0.))
+        } else {
+            let below_other = self.position.y-self.size.y/2. < other.position.y-other.size.y/2.;
+            Some(Vector3::new(0., if below_other { -overlap_y } else { overlap_y }, 0.))
+        }
+    }
+    //move the box along each axis in turn, stopping flush against the first solid tile or platform
+    pub fn sweep(&self, displacement: &Vector3, map: &TileMap, platforms: &[(Entity, Hitbox)]) -> Sweep {
+        let (tile_width, tile_height) = map.tile_size();
+        let mut contacts = Contacts::default();
+        let mut ground = None;
+        let (left, top) = (self.position.x, self.position.y);
+        let (right, bottom) = (left + self.size.x, top - self.size.y);
+
+        //horizontal pass, checking only the rows the box currently spans
+        let mut dx = displacement.x;
+        let (row_lo, row_hi) = (
+            ((bottom + EPSILON)/tile_height).floor() as i32,
+            ((top - EPSILON)/tile_height).floor() as i32
+        );
+        if dx > 0. {
+            let (col_lo, col_hi) = (
+                ((right - EPSILON)/tile_width).ceil() as i32,
+                ((right + dx + EPSILON)/tile_width).floor() as i32
+            );
+            if let Some(col) = (col_lo..=col_hi).find(|&col| (row_lo..=row_hi).any(|row| map.is_cell_collidable(col, row))) {
+                dx = (col as f32*tile_width - right).max(0.);
+                contacts.right = true;
+            }
+        } else if dx < 0. {
+            let (col_hi, col_lo) = (
+                ((left + EPSILON)/tile_width).floor() as i32 - 1,
+                ((left + dx - EPSILON)/tile_width).floor() as i32
+            );
+            if let Some(col) = (col_lo..=col_hi).rev().find(|&col| (row_lo..=row_hi).any(|row| map.is_cell_collidable(col, row))) {
+                dx = ((col+1) as f32*tile_width - left).min(0.);
+                contacts.left = true;
             }
         }
-        return false;
-    }
-    pub fn colliding_with_ground(&self, map: &TileMap) -> bool {
-        let num_iter_x = (self.size.x/map.tile_map_data.tilewidth as f32).ceil() as usize;
-        for x in 0..num_iter_x {
-            let pix_x = self.position.x + (x as f32)*map.tile_map_data.tilewidth as f32;
-            let pix_y = self.position.y - self.size.y;
-            if map.is_tile_collidable(map.xy_to_i(map.pix_to_map(pix_x), map.pix_to_map(pix_y))) {
-                return true;
+        for (_, platform) in platforms {
+            let (p_left, p_right) = (platform.position.x, platform.position.x + platform.size.x);
+            let (p_top, p_bottom) = (platform.position.y, platform.position.y - platform.size.y);
+            if p_bottom >= top - EPSILON || p_top <= bottom + EPSILON {
+                continue;
+            }
+            if dx > 0. && p_left >= right - EPSILON && p_left <= right + dx + EPSILON {
+                dx = (p_left - right).max(0.);
+                contacts.right = true;
+            } else if dx < 0. && p_right <= left + EPSILON && p_right >= left + dx - EPSILON {
+                dx = (p_right - left).min(0.);
+                contacts.left = true;
+            }
+        }
+
+        //vertical pass, using the columns the box spans after moving horizontally
+        let mut dy = displacement.y;
+        let (col_lo, col_hi) = (
+            ((left + dx + EPSILON)/tile_width).floor() as i32,
+            ((right + dx - EPSILON)/tile_width).floor() as i32
+        );
+        if dy > 0. {
+            let (row_lo, row_hi) = (
+                ((top - EPSILON)/tile_height).ceil() as i32,
+                ((top + dy + EPSILON)/tile_height).floor() as i32
+            );
+            if let Some(row) = (row_lo..=row_hi).find(|&row| (col_lo..=col_hi).any(|col| map.is_cell_collidable(col, row))) {
+                dy = (row as f32*tile_height - top).max(0.);
+                contacts.top = true;
+            }
+        } else if dy < 0. {
+            let (row_hi, row_lo) = (
+                ((bottom + EPSILON)/tile_height).floor() as i32 - 1,
+                ((bottom + dy - EPSILON)/tile_height).floor() as i32
+            );
+            if let Some(row) = (row_lo..=row_hi).rev().find(|&row| (col_lo..=col_hi).any(|col| map.is_cell_collidable(col, row))) {
+                dy = ((row+1) as f32*tile_height - bottom).min(0.);
+                contacts.bottom = true;
             }
         }
-        return false;
-    }
-    pub fn colliding_with_left_wall(&self, map: &TileMap) -> bool {
-        let num_iter_y = (self.size.y/map.tile_map_data.tileheight as f32).ceil() as usize;
-        for y in 0..num_iter_y {
-            let pix_x = self.position.x - 4.;
-            let pix_y = self.position.y - (y as f32)*map.tile_map_data.tileheight as f32 + 4.;
-            if map.is_tile_collidable(map.xy_to_i(map.pix_to_map(pix_x), map.pix_to_map(pix_y))) {
-                return true;
+        for (entity, platform) in platforms {
+            let (p_left, p_right) = (platform.position.x, platform.position.x + platform.size.x);
+            let (p_top, p_bottom) = (platform.position.y, platform.position.y - platform.size.y);
+            if p_right <= left + dx + EPSILON || p_left >= right + dx - EPSILON {
+                continue;
             }
+            if dy < 0. && p_top <= bottom + EPSILON && p_top >= bottom + dy - EPSILON {
+                dy = (p_top - bottom).min(0.);
+                contacts.bottom = true;
+                ground = Some(*entity);
+            } else if dy > 0. && p_bottom >= top - EPSILON && p_bottom <= top + dy + EPSILON {
+                dy = (p_bottom - top).max(0.);
+                contacts.top = true;
+            }
+        }
+
+        Sweep {
+            displacement: Vector3::new(dx, dy, 0.),
+            contacts,
+            ground
+        }
+    }
+}
+
+//marks an entity that other bodies cannot pass through, such as a crate or a shield
+#[derive(Default, Debug)]
+pub struct Solid;
+impl Component for Solid {
+    type Storage = NullStorage<Self>;
+}
+
+//published for every overlapping pair, penetration pushes `a` out of `b`
+#[derive(Debug, Clone)]
+pub struct EntityContact {
+    pub a: Entity,
+    pub b: Entity,
+    pub penetration: Vector3
+}
+
+//moves a body out of something solid, true when that set it down from a jump or fall
+pub fn push_out(physics: &mut Physics, correction: Vector3) -> bool {
+    physics.position += correction;
+    if correction.x * physics.velocity.x < 0. {
+        physics.velocity.x = 0.;
+    }
+    if correction.y * physics.velocity.y < 0. {
+        physics.velocity.y = 0.;
+    }
+    //pushed up means standing on top of the other body
+    if correction.y > 0. {
+        let landed = physics.is_jumping;
+        physics.contacts.bottom = true;
+        physics.is_jumping = false;
+        physics.airborne_time = 0.;
+        return landed;
+    }
+    false
+}
+
+pub struct EntityCollisionSystem {
+    grid: UniformGrid
+}
+impl EntityCollisionSystem {
+    pub fn new() -> EntityCollisionSystem {
+        EntityCollisionSystem {
+            grid: UniformGrid::new(64.)
+        }
+    }
+}
+impl Default for EntityCollisionSystem {
+    fn default() -> EntityCollisionSystem {
+        EntityCollisionSystem::new()
+    }
+}
+impl <'a> System<'a> for EntityCollisionSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Solid>,
+        WriteStorage<'a, Physics>,
+        Write<'a, EventChannel<EntityContact>>,
+        Write<'a, EventChannel<GameEvent>>,
+    );
+    fn run(&mut self, (entities, collision_boxes, solids, mut physics_set, mut contact_events, mut game_events): Self::SystemData) {
+        let bodies: Vec<(Entity, Hitbox)> = (&entities, &collision_boxes, &physics_set).join()
+            .map(|(entity, collision_box, physics)| (entity, collision_box.hitbox_at(&physics.position)))
+            .collect();
+        self.grid.clear();
+        for (id, (_, hitbox)) in bodies.iter().enumerate() {
+            self.grid.insert(id, hitbox);
         }
-        return false;
-    }
-    pub fn colliding_with_right_wall(&self, map: &TileMap) -> bool {
-        let num_iter_y = (self.size.y/map.tile_map_data.tileheight as f32).ceil() as usize;
-        for y in 0..num_iter_y {
-            let pix_x = self.position.x + self.size.x + 4.;
-            let pix_y = self.position.y - (y as f32)*map.tile_map_data.tileheight as f32 + 4.;
-            if map.is_tile_collidable(map.xy_to_i(map.pix_to_map(pix_x), map.pix_to_map(pix_y))) {
-                return true;
+        for (a, b) in self.grid.candidate_pairs() {
+            let ((entity_a, hb_a), (entity_b, hb_b)) = (&bodies[a], &bodies[b]);
+            if let Some(penetration) = hb_a.penetration(hb_b) {
+                contact_events.single_write(EntityContact {
+                    a: *entity_a,
+                    b: *entity_b,
+                    penetration
+                });
+                //solid bodies push the other one out, two solids split the difference
+                let (solid_a, solid_b) = (solids.contains(*entity_a), solids.contains(*entity_b));
+                let share = if solid_a && solid_b { 0.5 } else { 1. };
+                if solid_b {
+                    if let Some(physics) = physics_set.get_mut(*entity_a) {
+                        if push_out(physics, penetration*share) {
+                            game_events.single_write(GameEvent::Landed { entity: *entity_a });
+                        }
+                    }
+                }
+                if solid_a {
+                    if let Some(physics) = physics_set.get_mut(*entity_b) {
+                        if push_out(physics, -penetration*share) {
+                            game_events.single_write(GameEvent::Landed { entity: *entity_b });
+                        }
+                    }
+                }
             }
         }
-        return false;
+    }
+}
+
+//boxes built by hand for the tests of any module
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+
+    //top-left corner at x, y like every other hitbox
+    pub fn hitbox(x: f32, y: f32, width: f32, height: f32) -> Hitbox {
+        Hitbox {
+            position: Point3::new(x, y, 0.),
+            size: Vector3::new(width, height, 0.)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::{hitbox};
+    use crate::tilemap::{TileMapData, TileLayerData};
+
+    //rows from the top, '#' is solid, tiles are 16 pixels so the bottom row covers y 16 to 32
+    fn map(rows: &[&str]) -> TileMap {
+        let mut layer = TileLayerData::default();
+        layer.name = "collidable".to_string();
+        layer.data = rows.iter()
+            .flat_map(|row| row.chars().map(|c| if c == '#' { 1 } else { 0 }))
+            .collect();
+        let mut data = TileMapData::default();
+        data.width = rows[0].len();
+        data.height = rows.len();
+        data.tilewidth = 16;
+        data.tileheight = 16;
+        data.layers = vec![layer];
+        TileMap::new(data, Default::default())
+    }
+
+    fn level() -> TileMap {
+        map(&[
+            "........",
+            "........",
+            "....#...",
+            "########"
+        ])
+    }
+
+    #[test]
+    fn lands_flush_on_a_tile() {
+        let sweep = hitbox(8., 70., 14., 28.).sweep(&Vector3::new(0., -20., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement, Vector3::new(0., -10., 0.));
+        assert!(sweep.contacts.bottom);
+
+        //already resting on the floor, gravity keeps the contact without moving the box
+        let sweep = hitbox(8., 60., 14., 28.).sweep(&Vector3::new(0., -1., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement.y, 0.);
+        assert!(sweep.contacts.bottom);
+    }
+
+    #[test]
+    fn stops_at_an_exact_tile_edge() {
+        //right edge sits exactly on the left edge of the raised tile
+        let sweep = hitbox(50., 44., 14., 12.).sweep(&Vector3::new(5., 0., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement.x, 0.);
+        assert!(sweep.contacts.right);
+
+        //moving away from the wall is free
+        let sweep = hitbox(50., 44., 14., 12.).sweep(&Vector3::new(-5., 0., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement.x, -5.);
+        assert!(!sweep.contacts.left && !sweep.contacts.right);
+    }
+
+    #[test]
+    fn fast_moves_do_not_tunnel() {
+        let sweep = hitbox(8., 70., 14., 28.).sweep(&Vector3::new(0., -500., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement.y, -10.);
+        assert!(sweep.contacts.bottom);
+
+        let sweep = hitbox(20., 44., 14., 12.).sweep(&Vector3::new(300., 0., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement.x, 30.);
+        assert!(sweep.contacts.right);
+    }
+
+    #[test]
+    fn starting_inside_a_tile() {
+        //the sweep ignores tiles the box already overlaps, so it can always move out
+        let overlapping = hitbox(60., 44., 14., 12.);
+        let sweep = overlapping.sweep(&Vector3::new(-2., 0., 0.), &level(), &[]);
+        assert_eq!(sweep.displacement.x, -2.);
+        assert!(!sweep.contacts.left);
+
+        //penetration gives the shortest way out
+        let tile = hitbox(64., 48., 16., 16.);
+        assert_eq!(overlapping.penetration(&tile), Some(Vector3::new(-10., 0., 0.)));
+        assert_eq!(hitbox(66., 50., 12., 6.).penetration(&tile), Some(Vector3::new(0., 4., 0.)));
+    }
+
+    #[test]
+    fn touching_boxes_do_not_penetrate() {
+        let tile = hitbox(64., 48., 16., 16.);
+        assert_eq!(hitbox(50., 44., 14., 12.).penetration(&tile), None);
+        assert_eq!(hitbox(64., 60., 16., 12.).penetration(&tile), None);
     }
 }
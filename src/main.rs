@@ -1,11 +1,13 @@
 use amethyst::{
+    assets::{Processor},
+    config::Config,
     core::{
         transform::{TransformBundle}
     },
     input::{InputBundle, StringBindings},
     prelude::*,
     renderer::{
-        plugins::{RenderFlat2D, RenderToWindow},
+        plugins::{RenderToWindow},
         types::DefaultBackend,
         RenderingBundle,
     },
@@ -13,26 +15,43 @@ use amethyst::{
 };
 
 mod animation;
+mod camera;
 mod character;
 mod tilemap;
 mod state;
 mod system;
 mod hitbox;
+mod combat;
+mod tuning;
+mod environment;
+mod event;
 
 use crate::system::{
-    MovementSystem,
-    PhysicsSystem,
-    UpdateCameraSystem
+    InterpolationSystem
 };
-use crate::character::{
-    PlayerSystem
+use crate::camera::{
+    CameraConfig,
+    CameraScriptSystem,
+    RenderSplitScreen,
+    UpdateCameraSystem,
+    ViewportSystem
 };
 use crate::animation::{
     AnimationSystem
 };
+use crate::character::{
+    CharacterData
+};
+use crate::combat::{
+    ImpactConfig
+};
 use crate::state::{
     LoadMapState
 };
+use crate::tuning::{
+    ConfigReloadSystem,
+    PhysicsConfig
+};
 
 fn main() -> amethyst::Result<()> {
     amethyst::start_logger(Default::default());
@@ -42,6 +61,12 @@ fn main() -> amethyst::Result<()> {
     let assets_dir = app_root.join("assets");
     let config_dir = app_root.join("config");
     let display_config_path = config_dir.join("display.ron");
+    let impact_config = ImpactConfig::load(config_dir.join("impact.ron"));
+    let physics_config_path = config_dir.join("physics.ron");
+    let physics_config = PhysicsConfig::load(&physics_config_path);
+    let camera_config_path = config_dir.join("camera.ron");
+    let camera_config = CameraConfig::load(&camera_config_path);
+    let clear_colour = [0.34, 0.36, 0.52, 1.0];
 
     let game_data = GameDataBuilder::default()
         .with_bundle(TransformBundle::new())?
@@ -49,21 +74,30 @@ fn main() -> amethyst::Result<()> {
             InputBundle::<StringBindings>::new()
                 .with_bindings_from_file(config_dir.join("input.ron"))?
         )?
-        .with(MovementSystem, "movement_system", &[])
-        .with(PhysicsSystem, "physics_system", &["movement_system"])
-        .with(UpdateCameraSystem, "update_camera_system", &["movement_system"])
-        .with(PlayerSystem, "player_system", &["physics_system"])
-        .with(AnimationSystem, "animation_system", &["player_system"])
+        .with(Processor::<CharacterData>::new(), "character_data_processor", &[])
+        //movement, physics and player logic run on the fixed timestep inside GameState
+        .with(ConfigReloadSystem::<PhysicsConfig>::new(physics_config_path), "physics_config_reload_system", &[])
+        .with(ConfigReloadSystem::<CameraConfig>::new(camera_config_path), "camera_config_reload_system", &[])
+        .with(InterpolationSystem, "interpolation_system", &[])
+        .with(ViewportSystem::default(), "viewport_system", &[])
+        .with(CameraScriptSystem::default(), "camera_script_system", &[])
+        .with(UpdateCameraSystem, "update_camera_system", &["interpolation_system", "viewport_system", "camera_script_system"])
+        .with(AnimationSystem, "animation_system", &["interpolation_system"])
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
                 .with_plugin(
                     RenderToWindow::from_config_path(display_config_path)
-                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                        .with_clear(clear_colour),
                 )
-                .with_plugin(RenderFlat2D::default()),
+                //sprites, through one shared camera or a split view per player
+                .with_plugin(RenderSplitScreen::default().with_clear(clear_colour)),
         )?;
 
-    let mut game = Application::new(assets_dir, LoadMapState::new(), game_data)?;
+    let mut game = Application::build(assets_dir, LoadMapState::new())?
+        .with_resource(impact_config)
+        .with_resource(physics_config)
+        .with_resource(camera_config)
+        .build(game_data)?;
     game.run();
 
     Ok(())
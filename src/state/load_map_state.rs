@@ -1,6 +1,6 @@
 
 use amethyst::{
-    assets::{AssetStorage, Handle, Loader, ProgressCounter},
+    assets::{AssetStorage, Handle, Loader, ProgressCounter, RonFormat},
     prelude::*,
     renderer::{
         formats::texture::ImageFormat,
@@ -12,6 +12,7 @@ use amethyst::{
 use std::fs;
 use std::collections::HashMap;
 
+use crate::character::{CharacterData, CharacterResource};
 use crate::state::main_state::{GameState};
 use crate::tilemap::{TileMapData};
 
@@ -32,6 +33,16 @@ fn load_sprite_sheet(world: &World, file_name: &str, pc: &mut ProgressCounter) -
     )
 }
 
+fn load_character(world: &World, file_name: &str, pc: &mut ProgressCounter) -> Handle<CharacterData> {
+    let loader = world.read_resource::<Loader>();
+    loader.load(
+        format!("characters/{}.ron", file_name),
+        RonFormat,
+        pc,
+        &world.read_resource::<AssetStorage<CharacterData>>()
+    )
+}
+
 pub fn load_tile_set(world: &World, file_name: &str, tile_map_data: &TileMapData, pc: &mut ProgressCounter) -> Handle<SpriteSheet> {
     let texture_handle = {
         let loader = world.read_resource::<Loader>();
@@ -107,11 +118,25 @@ impl SimpleState for LoadMapState {
             self.progress_counters.push(pc);
         }
         //load sprites
-        let (mut pc1, mut pc2) = (ProgressCounter::new(), ProgressCounter::new());
-        self.sprite_handles.insert("player_sprite_sheet".to_string(), load_sprite_sheet(&world, "player_sprite_sheet", &mut pc1));
-        self.sprite_handles.insert("enemy_kobold_sprite_sheet".to_string(), load_sprite_sheet(&world, "enemy_kobold_sprite_sheet", &mut pc2));
-        self.progress_counters.push(pc1);
-        self.progress_counters.push(pc2);
+        let mut pc = ProgressCounter::new();
+        self.sprite_handles.insert("projectile_sprite_sheet".to_string(), load_sprite_sheet(&world, "projectile_sprite_sheet", &mut pc));
+        self.progress_counters.push(pc);
+        //load characters, every file in the folder is one named after it with its art in <name>_sprite_sheet
+        let mut character_resource = CharacterResource::default();
+        for entry in fs::read_dir("assets/characters").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map_or(true, |extension| extension != "ron") {
+                continue;
+            }
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let sprite_sheet = format!("{}_sprite_sheet", name);
+            let (mut pc1, mut pc2) = (ProgressCounter::new(), ProgressCounter::new());
+            self.sprite_handles.insert(sprite_sheet.clone(), load_sprite_sheet(&world, &sprite_sheet, &mut pc1));
+            character_resource.insert(name.clone(), load_character(&world, &name, &mut pc2));
+            self.progress_counters.push(pc1);
+            self.progress_counters.push(pc2);
+        }
+        world.add_resource(character_resource);
     }
 
     fn update(&mut self, _data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
@@ -122,7 +147,8 @@ impl SimpleState for LoadMapState {
                 tile_map_data: self.tile_map_data.clone(),
                 tile_set_handles: self.tile_set_handles.clone(),
                 sprite_handles: self.sprite_handles.clone(),
-                map_entities: Vec::new()
+                map_entities: Vec::new(),
+                fixed_dispatcher: None
             }))
         } else {
             Trans::None
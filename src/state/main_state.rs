@@ -1,14 +1,16 @@
 
 use amethyst::{
-    assets::{Handle},
+    assets::{AssetStorage, Handle},
     core::{
         math as na,
+        timing::{Time},
         transform::{Transform}
     },
-    ecs::{Entity},
+    ecs::{Entity, EntityBuilder, Dispatcher, DispatcherBuilder, Join},
     prelude::*,
+    input::{InputHandler, StringBindings},
     renderer::{
-        Camera,
+        ActiveCamera, Camera,
         SpriteRender, SpriteSheet,
         Transparent
     }
@@ -16,96 +18,205 @@ use amethyst::{
 
 use std::collections::HashMap;
 
-use crate::system::{Physics, CameraSettings};
-use crate::character::{Player, CharacterType};
-use crate::animation::{SpriteAnimation, AnimationType, AnimationResource};
+use crate::system::{Physics, MovementSystem, PhysicsSystem};
+use crate::camera::{CameraConfig, CameraSettings, CameraTarget, SplitView, create_letterbox};
+use crate::character::{Player, Abilities, CharacterName, CharacterData, CharacterResource, PlayerSystem};
+use crate::animation::{SpriteAnimation, AnimationType};
 use crate::tilemap::{TileMapData, TileMap};
+use crate::tuning::{PhysicsConfig};
+use crate::combat::{
+    MeleeAttack, MeleeSystem, Health, HealthSystem, Respawn, ImpactSystem, HitStop,
+    Projectile, ProjectileType, ProjectilePool, ProjectileSystem, RangedAttack, RangedAttackSystem
+};
+use crate::hitbox::{CollisionBoxSystem, EntityCollisionSystem, Solid};
+use crate::environment::{WaterVolumes, WaterSystem, Breath, Checkpoints, HazardSystem, Platform, PlatformSystem, create_platforms, TriggerSystem, create_triggers};
 
 type Vector3 = na::Vector3<f32>;
 
-fn init_player_sprite(world: &mut World, sprite_sheet_handle: &Handle<SpriteSheet>) {
+//the components every character gets from its data in assets/characters, None if there is no such character
+fn character_builder<'a>(world: &'a mut World, sprite_handles: &HashMap<String, Handle<SpriteSheet>>, name: &str, position: Vector3) -> Option<EntityBuilder<'a>> {
+    let name = CharacterName(name.to_string());
+    let (data, animation, collision_box, anim_type) = {
+        let (character_resource, character_data) = (world.read_resource::<CharacterResource>(), world.read_resource::<AssetStorage<CharacterData>>());
+        let data = match character_resource.get(&character_data, &name) {
+            Some(data) => data.clone(),
+            None => {
+                log::warn!("there is no character named {}", name.0);
+                return None;
+            }
+        };
+        let anim_type = if data.always_attacking { AnimationType::Attack(0) } else { AnimationType::Idle };
+        let animation = SpriteAnimation::from_data(character_resource.animation(&character_data, &name, &anim_type));
+        let collision_box = character_resource.collision_box(&character_data, &name, &anim_type);
+        (data, animation, collision_box, anim_type)
+    };
     let mut sprite_transform = Transform::default();
-    sprite_transform.set_translation_xyz(30., 64., 0.);
+    sprite_transform.set_translation(position);
     let sprite_render = SpriteRender {
-        sprite_sheet: sprite_sheet_handle.clone(),
+        sprite_sheet: sprite_handles.get(&format!("{}_sprite_sheet", name.0))?.clone(),
         sprite_number: 0
     };
-    let char_type = CharacterType::Player;
-    let anim_type = AnimationType::Idle;
-    let animation_data =  world.read_resource::<AnimationResource>().data(&char_type, &anim_type);
-    let animation = SpriteAnimation::from_data(animation_data);
-    world.create_entity()
+    let builder = world.create_entity()
         .with(sprite_render)
         .with(sprite_transform)
         .with(animation)
-        .with(Physics::default())
-        .with(Player::new())
-        .with(char_type)
+        .with(collision_box)
+        .with(MeleeAttack::default())
+        .with(Physics::at(position))
+        .with(Health::new(data.health, data.invulnerability))
+        .with(data.side)
+        .with(name)
         .with(anim_type)
-        .with(Transparent)
-        .build();
+        .with(Transparent);
+    Some(if data.solid { builder.with(Solid) } else { builder })
 }
 
-fn init_enemy_sprite(world: &mut World, sprite_sheet_handle: &Handle<SpriteSheet>) {
-    let mut sprite_transform = Transform::default();
-    sprite_transform.set_translation_xyz(220., 64., 0.);
-    let sprite_render = SpriteRender {
-        sprite_sheet: sprite_sheet_handle.clone(),
-        sprite_number: 0
-    };
-    let char_type = CharacterType::Enemy;
-    let anim_type = AnimationType::Attack(0);
-    let animation_data =  world.read_resource::<AnimationResource>().data(&char_type, &anim_type);
-    let animation = SpriteAnimation::from_data(animation_data);
-    world.create_entity()
-        .with(sprite_render)
-        .with(sprite_transform)
-        .with(animation)
-        .with(Physics::default())
-        .with(char_type)
-        .with(anim_type)
-        .with(Transparent)
-        .build();
+fn init_player_sprite(world: &mut World, sprite_handles: &HashMap<String, Handle<SpriteSheet>>, index: usize, position: Vector3) {
+    let breath = world.read_resource::<PhysicsConfig>().water.breath;
+    if let Some(builder) = character_builder(world, sprite_handles, "player", position) {
+        builder
+            .with(Breath::new(breath))
+            .with(RangedAttack::new(ProjectileType::Knife, 0.25, 0.))
+            .with(Respawn { position })
+            .with(Player::new(index))
+            .with(CameraTarget::new(position))
+            .with(Abilities { air_jump: true, dash: true })
+            .build();
+    }
 }
 
-fn init_camera(world: &mut World, width: f32, height: f32) {
+fn init_camera(world: &mut World, camera_settings: &mut CameraSettings) {
+    //start in the bottom-left corner of the map
+    let ((width, height), bounds) = (camera_settings.viewport, &camera_settings.bounds);
+    camera_settings.position = Vector3::new(bounds.position.x + width/2., bounds.position.y - bounds.size.y + height/2., 20.);
     let mut transform = Transform::default();
-    transform.set_translation_xyz(width/2.-8., height/2., 20.);
-    let camera = Camera::standard_2d(width, height);
-    world.create_entity()
-        .with(camera)
-        .with(transform)
+    transform.set_translation(camera_settings.position);
+    let camera = world.create_entity()
+        .with(Camera::standard_2d(width, height))
+        .with(transform.clone())
         .build();
+    create_letterbox(world, camera);
+
+    //one view per co-op player for split-screen, drawn side by side while the players are apart
+    for index in 0..2 {
+        world.create_entity()
+            .with(Camera::standard_2d(width/2., height))
+            .with(SplitView::new(index, camera_settings.position))
+            .with(transform.clone())
+            .build();
+    }
+    world.add_resource(ActiveCamera { entity: Some(camera) });
+}
+
+//gameplay systems that must run at a fixed rate regardless of frame rate
+fn build_fixed_dispatcher(world: &mut World) -> Dispatcher<'static, 'static> {
+    let mut dispatcher = DispatcherBuilder::new()
+        .with(WaterSystem, "water_system", &[])
+        .with(MovementSystem, "movement_system", &["water_system"])
+        .with(PhysicsSystem, "physics_system", &["movement_system"])
+        .with(PlatformSystem, "platform_system", &["physics_system"])
+        .with(EntityCollisionSystem::new(), "entity_collision_system", &["platform_system"])
+        .with(PlayerSystem, "player_system", &["entity_collision_system"])
+        .with(CollisionBoxSystem, "collision_box_system", &["player_system"])
+        .with(MeleeSystem, "melee_system", &["player_system"])
+        .with(RangedAttackSystem, "ranged_attack_system", &["player_system"])
+        .with(ProjectileSystem::default(), "projectile_system", &["ranged_attack_system"])
+        .with(HazardSystem, "hazard_system", &["entity_collision_system"])
+        .with(TriggerSystem, "trigger_system", &["entity_collision_system"])
+        .with(ImpactSystem::default(), "impact_system", &["melee_system", "projectile_system", "hazard_system"])
+        .with(HealthSystem::default(), "health_system", &["impact_system"])
+        .build();
+    dispatcher.setup(&mut world.res);
+    dispatcher
 }
 
-#[derive(Default, Debug)]
+#[derive(Default)]
 pub struct GameState {
     pub tile_map_data: TileMapData,
     pub tile_set_handles: HashMap<usize, Handle<SpriteSheet>>,
     pub sprite_handles: HashMap<String, Handle<SpriteSheet>>,
     pub map_entities: Vec<Entity>,
+    pub fixed_dispatcher: Option<Dispatcher<'static, 'static>>,
 }
 impl SimpleState for GameState {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         let mut world = data.world;
-
-        let (map_width, map_height, tile_width, tile_height) = (
-            self.tile_map_data.width, self.tile_map_data.height,
-            self.tile_map_data.tilewidth, self.tile_map_data.tileheight
-        );
-        let pix_map_size = Vector3::new(
-            (map_width*tile_width) as f32, (map_height*tile_height) as f32, 0.
-        );
-        let camera_settings = CameraSettings::new(Vector3::new(30., 30., 20.), pix_map_size);
-        init_camera(&mut world, camera_settings.viewport.0, camera_settings.viewport.1);
+        self.fixed_dispatcher = Some(build_fixed_dispatcher(&mut world));
 
         let tile_map = TileMap::new(self.tile_map_data.clone(), self.tile_set_handles.clone());
+        let mut camera_settings = CameraSettings::new(Vector3::new(30., 30., 20.), tile_map.bounds());
+        camera_settings.regions = tile_map.objects("camera").into_iter()
+            .map(|object| tile_map.object_hitbox(object))
+            .collect();
+        camera_settings.viewport = world.read_resource::<CameraConfig>().viewport;
+        init_camera(&mut world, &mut camera_settings);
+
         tile_map.build_map(&mut world);
+        create_platforms(&mut world, &tile_map);
+        create_triggers(&mut world, &tile_map);
+
+        //characters placed on the map by name, the player joins separately
+        let characters: Vec<(String, Vector3)> = tile_map.objects("character").into_iter()
+            .filter_map(|object| object.property("character")
+                .and_then(|value| value.as_str())
+                .map(|name| (name.to_string(), tile_map.tiled_to_world(object.x, object.y))))
+            .collect();
 
+        world.add_resource(WaterVolumes::from_map(&tile_map));
+        world.add_resource(Checkpoints::from_map(&tile_map));
         world.add_resource(tile_map);
         world.add_resource(camera_settings);
+        world.add_resource(ProjectilePool::new(self.sprite_handles.get("projectile_sprite_sheet").unwrap().clone()));
+
+        init_player_sprite(&mut world, &self.sprite_handles, 0, Vector3::new(30., 64., 0.));
+        for (name, position) in characters {
+            if let Some(builder) = character_builder(&mut world, &self.sprite_handles, &name, position) {
+                builder.build();
+            }
+        }
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        //second player joins next to the first by pressing their jump
+        let joining = data.world.read_resource::<InputHandler<StringBindings>>().action_is_down("jump_2").unwrap_or(false);
+        if joining {
+            let position = {
+                let (players, physics_set) = (data.world.read_storage::<Player>(), data.world.read_storage::<Physics>());
+                let joined = (&players).join().any(|player| player.index == 1);
+                (&players, &physics_set).join()
+                    .find(|(player, _)| player.index == 0)
+                    .map(|(_, physics)| physics.position)
+                    .filter(|_| !joined)
+            };
+            if let Some(position) = position {
+                init_player_sprite(data.world, &self.sprite_handles, 1, position);
+            }
+        }
+        Trans::None
+    }
 
-        init_player_sprite(&mut world, &self.sprite_handles.get("player_sprite_sheet").unwrap());
-        init_enemy_sprite(&mut world, &self.sprite_handles.get("enemy_kobold_sprite_sheet").unwrap());
+    fn fixed_update(&mut self, data: StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let world = data.world;
+        let hit_stop_active = {
+            let mut hit_stop = world.write_resource::<HitStop>();
+            let active = hit_stop.is_active();
+            hit_stop.remaining = (hit_stop.remaining - world.read_resource::<Time>().fixed_seconds()).max(0.);
+            active
+        };
+        if hit_stop_active {
+            //hold every body still, including its interpolation
+            for physics in (&mut world.write_storage::<Physics>()).join() {
+                physics.previous_position = physics.position;
+            }
+            for platform in (&mut world.write_storage::<Platform>()).join() {
+                platform.previous_position = platform.position;
+            }
+            for projectile in (&mut world.write_storage::<Projectile>()).join() {
+                projectile.previous_position = projectile.position;
+            }
+        } else if let Some(dispatcher) = self.fixed_dispatcher.as_mut() {
+            dispatcher.dispatch(&world.res);
+        }
+        Trans::None
     }
 }
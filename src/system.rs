@@ -1,82 +1,76 @@
 use amethyst::{
+    assets::{AssetStorage},
     core::{
         math as na,
         timing::{Time},
         transform::{Transform}
     },
-    ecs::{Component, System, Join, DenseVecStorage},
+    ecs::{Component, System, Join, DenseVecStorage, Entity},
     ecs::prelude::{
+        Entities,
         Read,
         Write,
         ReadStorage,
         WriteStorage
     },
-    renderer::{
-        Camera
-    },
     input::{InputHandler, StringBindings},
+    shrev::{EventChannel}
 };
 
-use crate::character::{Player, CharacterType};
-use crate::hitbox::{Hitbox};
-use crate::tilemap::{TileMap};
+use crate::camera::{CameraConfig};
+use crate::character::{Player, Abilities, CharacterType, CharacterName, CharacterData, CharacterResource};
+use crate::combat::{Health, Frozen, Projectile};
+use crate::environment::{Platform};
+use crate::event::{GameEvent};
+use crate::animation::{AnimationType};
+use crate::hitbox::{CollisionBox, Contacts, Hitbox};
+use crate::tilemap::{TileMap, TileProperty};
+use crate::tuning::{PhysicsConfig, SurfaceMaterial};
 
-type Point3 = na::Point3<f32>;
 type Vector3 = na::Vector3<f32>;
 
 #[derive(Debug)]
-pub struct CameraSettings {
-    pub boundaries: Vector3,
-    pub target: Vector3,
+pub struct Physics {
+    pub position: Vector3,
+    pub previous_position: Vector3,
+    pub acceleration: Vector3,
     pub velocity: Vector3,
-    pub viewport: (f32, f32)
+    pub mass: f32,
+    pub friction: f32,
+    pub is_jumping: bool,
+    //seconds since the last ground contact
+    pub airborne_time: f32,
+    //downward speed limit, set each step by whatever is holding the body up
+    pub max_fall_speed: Option<f32>,
+    pub gravity_scale: f32,
+    pub friction_scale: f32,
+    //set by the water system, swaps gravity for buoyancy and drag
+    pub in_water: bool,
+    //platform the body is standing on, it gets carried along
+    pub platform: Option<Entity>,
+    pub contacts: Contacts,
+    pub facing: f32
 }
-impl CameraSettings {
-    pub fn new(target: Vector3, boundaries: Vector3) -> CameraSettings {
-        CameraSettings {
-            target,
-            boundaries,
-            velocity: Vector3::new(0., 0., 0.),
-            viewport: (400., 300.)
+impl Physics {
+    pub fn at(position: Vector3) -> Physics {
+        Physics {
+            position,
+            previous_position: position,
+            ..Physics::default()
         }
     }
-}
-impl Default for CameraSettings {
-    fn default() -> CameraSettings {
-        CameraSettings::new(Vector3::new(0.,0.,0.), Vector3::new(800.,600.,0.))
-    }
-}
-
-pub struct UpdateCameraSystem;
-impl <'a> System<'a> for UpdateCameraSystem {
-    type SystemData = (
-        ReadStorage<'a, Camera>,
-        WriteStorage<'a, Transform>,
-        Read<'a, CameraSettings>,
-        Read<'a, Time>,
-    );
-    fn run(&mut self, (cameras, mut transform_set, camera_settings, time): Self::SystemData) {
-        let dt = time.delta_seconds();
-        for (_, transform) in (&cameras, &mut transform_set).join() {
-            let disp = (camera_settings.target - transform.translation())*dt;
-            if disp.norm() > 0. {
-                let mut translate = transform.translation().clone() + Vector3::new(disp.x, disp.y, 0.);
-                translate.x = translate.x.min(camera_settings.boundaries.x - camera_settings.viewport.0/2. - 8.).max(camera_settings.viewport.0/2. - 8.);
-                translate.y = translate.y.min(camera_settings.boundaries.y - camera_settings.viewport.1/2.).max(camera_settings.viewport.1/2.);
-                transform.set_translation(translate);
-            }
+    pub fn apply_impulse(&mut self, impulse: Vector3) {
+        self.velocity += impulse/self.mass;
+        if impulse.y > 0. {
+            self.is_jumping = true;
         }
     }
-}
-
-#[derive(Debug)]
-pub struct Physics {
-    pub acceleration: Vector3,
-    pub velocity: Vector3,
-    pub mass: f32,
-    pub friction: f32,
-    pub jump_cooldown: u32,
-    pub is_jumping: bool
+    //move without interpolating from the old position
+    pub fn teleport(&mut self, position: Vector3) {
+        self.position = position;
+        self.previous_position = position;
+        self.velocity = Vector3::new(0., 0., 0.);
+    }
 }
 impl Component for Physics {
     type Storage = DenseVecStorage<Self>;
@@ -84,97 +78,149 @@ impl Component for Physics {
 impl Default for Physics {
     fn default() -> Physics {
         Physics {
+            position: Vector3::new(0., 0., 0.),
+            previous_position: Vector3::new(0., 0., 0.),
             acceleration: Vector3::new(0., 0., 0.),
             velocity: Vector3::new(0., 0., 0.),
             mass: 1.,
             friction: 0.5,
-            jump_cooldown: 0,
-            is_jumping: false
+            is_jumping: false,
+            airborne_time: 0.,
+            max_fall_speed: None,
+            gravity_scale: 1.,
+            friction_scale: 1.,
+            in_water: false,
+            platform: None,
+            contacts: Contacts::default(),
+            facing: 1.
         }
     }
 }
 
+//runs on the fixed timestep, all units are pixels and seconds
 pub struct PhysicsSystem;
 impl <'a> System<'a> for PhysicsSystem {
     type SystemData = (
+        Entities<'a>,
         ReadStorage<'a, CharacterType>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Frozen>,
+        ReadStorage<'a, Platform>,
         WriteStorage<'a, Physics>,
-        WriteStorage<'a, Transform>,
-        Write<'a, CameraSettings>,
+        Write<'a, EventChannel<GameEvent>>,
         Read<'a, TileMap>,
+        Read<'a, PhysicsConfig>,
         Read<'a, Time>,
     );
-    fn run(&mut self, (character_types, mut physics_set, mut transform, mut camera_settings, tilemap, time): Self::SystemData) {
-        let dt = time.delta_seconds();
-        for (character_type, physics, transform) in (&character_types, &mut physics_set, &mut transform).join() {
-            let trans = transform.translation();
-            let hb = Hitbox {
-                position: Point3::new(trans.x, trans.y, 0.),
-                size: Vector3::new(12., 10., 0.)
-            };
-            let new_trans = transform.translation() + physics.velocity*dt + physics.acceleration*dt*dt/2.;
-            let new_hb = Hitbox {
-                position: Point3::new(new_trans.x, new_trans.y, 0.),
-                size: Vector3::new(12., 10., 0.)
-            };
-            let dist = trans - new_trans;
-            let total_hb = {
-                let tl_hb = if trans.x <= new_trans.x && trans.y <= new_trans.y { hb } else { new_hb };
-                Hitbox {
-                    position: Point3::new(tl_hb.position.x, tl_hb.position.y, 0.),
-                    size: Vector3::new(dist.x.abs() + tl_hb.size.x, dist.y.abs() + tl_hb.size.y, 0.)
-                }
+    fn run(&mut self, (entities, character_types, collision_boxes, frozen_set, platforms, mut physics_set, mut game_events, tilemap, physics_config, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        let platform_hitboxes: Vec<(Entity, Hitbox)> = (&entities, &platforms).join()
+            .map(|(entity, platform)| (entity, platform.hitbox()))
+            .collect();
+        let water_tuning = &physics_config.water;
+        for (entity, character_type, collision_box, frozen, physics) in (&entities, &character_types, &collision_boxes, frozen_set.maybe(), &mut physics_set).join() {
+            physics.previous_position = physics.position;
+            if frozen.is_some() {
+                continue;
+            }
+            let tuning = physics_config.tuning(character_type);
+            //gravity always pulls so resting characters keep reporting ground contact
+            physics.acceleration.y = tuning.gravity*physics.gravity_scale;
+            if physics.in_water {
+                physics.acceleration.y = physics.acceleration.y*water_tuning.gravity_scale + water_tuning.buoyancy*physics.gravity_scale;
+            }
+            //the tile just below the feet decides the surface material while standing
+            let hb = collision_box.hitbox_at(&physics.position);
+            let material = if physics.contacts.bottom {
+                tilemap.surface_at(hb.position.x + hb.size.x/2., hb.position.y - hb.size.y - 1.)
+                    .and_then(|property| physics_config.surfaces.material(property))
+                    .cloned()
+                    .unwrap_or_default()
+            } else {
+                SurfaceMaterial::default()
             };
+            let mut acceleration = physics.acceleration;
+            acceleration.x *= material.acceleration;
+            physics.velocity += acceleration*dt;
+            //friction pulls toward the surface's own speed, so conveyors carry whatever stands on them
+            physics.velocity.x -= (physics.velocity.x - material.speed)*physics.friction*physics.friction_scale*material.friction*tuning.friction*dt;
+            if physics.in_water {
+                physics.velocity -= physics.velocity*(water_tuning.drag*dt).min(1.);
+            }
+            if let Some(max_fall_speed) = physics.max_fall_speed {
+                physics.velocity.y = physics.velocity.y.max(-max_fall_speed);
+            }
 
-            let (left, top, right, bottom) = (
-                total_hb.colliding_with_left_wall(&tilemap),
-                total_hb.colliding_with_ceiling(&tilemap),
-                total_hb.colliding_with_right_wall(&tilemap),
-                total_hb.colliding_with_ground(&tilemap),
-            );
+            //sweep the collision box through the tile grid and snap to any surface it hits
+            let sweep = hb.sweep(&(physics.velocity*dt), &tilemap, &platform_hitboxes);
+            let normal = sweep.contacts.normal();
+            //cancel any velocity pointing into a contacted surface
+            if normal.x * physics.velocity.x < 0. {
+                physics.velocity.x = 0.;
+            }
+            if normal.y * physics.velocity.y < 0. {
+                physics.velocity.y = 0.;
+            }
 
-            if bottom {
-                if physics.velocity.y < 0. {
-                    physics.acceleration.y = 0.;
-                    physics.velocity.y = -dist.y;
-                    if physics.is_jumping {
-                        physics.is_jumping = false;
-                        physics.jump_cooldown = 6; //5 frames
-                    }
+            if sweep.contacts.bottom {
+                physics.airborne_time = 0.;
+                if physics.is_jumping {
+                    physics.is_jumping = false;
+                    game_events.single_write(GameEvent::Landed { entity });
                 }
             } else {
+                physics.airborne_time += dt;
                 if !physics.is_jumping {
                     physics.is_jumping = true;
                 }
-                physics.acceleration.y = -9.8;
-            }
-            if left && physics.velocity.x < 0. {
-                physics.acceleration.x = 0.;
-                physics.velocity.x = -dist.x;
-            }
-            if right && physics.velocity.x > 0. {
-                physics.acceleration.x = 0.;
-                physics.velocity.x = -dist.x;
             }
-            if top && physics.velocity.y > 0. {
-                physics.velocity.y = -dist.y;
+            physics.contacts = sweep.contacts;
+            physics.platform = sweep.ground;
+            if physics.velocity.x > 6. {
+                physics.facing = 1.;
+            } else if physics.velocity.x < -6. {
+                physics.facing = -1.;
             }
 
-            if physics.jump_cooldown > 0 {
-                physics.jump_cooldown -= 1;
-            }
-            physics.velocity += physics.acceleration*dt;
-            physics.velocity.x -= physics.velocity.x*physics.friction*14.*dt;
-            //clamp translation so new coordinates are always on the screen
-            let mut new_translation = transform.translation() + physics.velocity;
-            new_translation.x = new_translation.x.max(0.);
-            new_translation.y = new_translation.y.max(0.);
+            //keep characters from walking off the left edge, falling out the bottom is left to the kill plane
+            let mut new_position = physics.position + sweep.displacement;
+            new_position.x = new_position.x.max(0.);
+            physics.position = new_position;
+        }
+    }
+}
 
-            transform.set_translation(new_translation);
-            //target player with camera after updating position
-            if let CharacterType::Player = character_type {
-                camera_settings.target = new_translation;
-            }
+//runs every frame, places sprites between the last two physics steps
+pub struct InterpolationSystem;
+impl <'a> System<'a> for InterpolationSystem {
+    type SystemData = (
+        ReadStorage<'a, Physics>,
+        ReadStorage<'a, Platform>,
+        ReadStorage<'a, Projectile>,
+        WriteStorage<'a, Transform>,
+        Read<'a, CameraConfig>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (physics_set, platforms, projectiles, mut transforms, camera_config, time): Self::SystemData) {
+        let alpha = time.interpolation_alpha();
+        //pixel perfect mode keeps every sprite on a whole world pixel
+        let place = |transform: &mut Transform, position: Vector3| {
+            let (x, y) = if camera_config.pixel_perfect {
+                (position.x.round(), position.y.round())
+            } else {
+                (position.x, position.y)
+            };
+            let z = transform.translation().z;
+            transform.set_translation_xyz(x, y, z);
+        };
+        for (physics, transform) in (&physics_set, &mut transforms).join() {
+            place(transform, physics.previous_position.lerp(&physics.position, alpha));
+        }
+        for (platform, transform) in (&platforms, &mut transforms).join() {
+            place(transform, platform.previous_position.lerp(&platform.position, alpha));
+        }
+        for (projectile, transform) in (&projectiles, &mut transforms).join() {
+            place(transform, projectile.previous_position.lerp(&projectile.position, alpha));
         }
     }
 }
@@ -182,24 +228,193 @@ impl <'a> System<'a> for PhysicsSystem {
 pub struct MovementSystem;
 impl <'a> System<'a> for MovementSystem {
     type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, CharacterType>,
+        ReadStorage<'a, CharacterName>,
+        ReadStorage<'a, CollisionBox>,
         WriteStorage<'a, Player>,
         WriteStorage<'a, Physics>,
-        Read<'a, InputHandler<StringBindings>>
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Abilities>,
+        ReadStorage<'a, Frozen>,
+        Write<'a, EventChannel<GameEvent>>,
+        Read<'a, TileMap>,
+        Read<'a, CharacterResource>,
+        Read<'a, AssetStorage<CharacterData>>,
+        Read<'a, PhysicsConfig>,
+        Read<'a, InputHandler<StringBindings>>,
+        Read<'a, Time>,
     );
-    fn run(&mut self, (mut players, mut physics_set, input): Self::SystemData) {
-        let (cx, _cy, attack, jump) = (
-            input.axis_value("x").unwrap(),
-            input.axis_value("y").unwrap(),
-            input.action_is_down("attack").unwrap(),
-            input.action_is_down("jump").unwrap()
+    fn run(&mut self, (entities, character_types, character_names, collision_boxes, mut players, mut physics_set, health_set, abilities_set, frozen_set, mut game_events, tilemap, character_resource, character_data, physics_config, input, time): Self::SystemData) {
+        let dt = time.fixed_seconds();
+        let (jump_tuning, wall_tuning, ability_tuning, slide_tuning, climb_tuning, water_tuning) = (
+            &physics_config.jump, &physics_config.wall, &physics_config.abilities, &physics_config.slide, &physics_config.climb, &physics_config.water
         );
-        for (player, physics) in (&mut players, &mut physics_set).join() {
-            physics.acceleration.x = cx as f32 * 12.;
+        //frozen players keep their input and timers as they were until the freeze ends
+        for (entity, character_type, name, collision_box, player, physics, health, abilities, _) in (&entities, &character_types, &character_names, &collision_boxes, &mut players, &mut physics_set, health_set.maybe(), abilities_set.maybe(), !&frozen_set).join() {
+            let (cx, cy, attack, jump, dash) = (
+                input.axis_value(&player.binding("x")).unwrap_or(0.),
+                input.axis_value(&player.binding("y")).unwrap_or(0.),
+                input.action_is_down(&player.binding("attack")).unwrap_or(false),
+                input.action_is_down(&player.binding("jump")).unwrap_or(false),
+                input.action_is_down(&player.binding("dash")).unwrap_or(false)
+            );
+            if health.map_or(false, |health| health.is_dead()) {
+                physics.acceleration.x = 0.;
+                physics.gravity_scale = 1.;
+                player.climbing = false;
+                continue;
+            }
+            let tuning = physics_config.tuning(character_type);
+            let (can_air_jump, can_dash) = abilities.map_or((false, false), |abilities| (abilities.air_jump, abilities.dash));
+
+            let jump_released = player.buffer_jump(jump, jump_tuning, dt);
+
+            //a dash holds a straight line with gravity switched off until it runs out
+            let dash_pressed = dash && !player.dash_held;
+            player.dash_held = dash;
+            player.dash_cooldown = (player.dash_cooldown - dt).max(0.);
+            if physics.contacts.bottom {
+                player.dash_spent = false;
+            }
+            if dash_pressed && can_dash && !player.is_dashing() && !player.dash_spent && player.dash_cooldown <= 0. && !player.is_attacking {
+                player.dash_timer = ability_tuning.dash_duration;
+                player.dash_cooldown = ability_tuning.dash_cooldown;
+                //only one dash per trip through the air
+                player.dash_spent = physics.is_jumping;
+                player.climbing = false;
+            }
+            if player.is_dashing() {
+                player.dash_timer = (player.dash_timer - dt).max(0.);
+                physics.velocity = Vector3::new(physics.facing*ability_tuning.dash_speed, 0., 0.);
+                physics.acceleration.x = 0.;
+                physics.gravity_scale = 0.;
+                continue;
+            }
+            physics.gravity_scale = 1.;
+
+            //ladders hold the character with gravity off, up and down climb and jump lets go
+            let center = physics.position + collision_box.offset;
+            let on_ladder = tilemap.has_property_at(TileProperty::Climbable, center.x, center.y);
+            let grab = cy > 0.5 || (cy < -0.5 && !physics.contacts.bottom);
+            if !player.climbing && on_ladder && grab && !player.is_attacking {
+                player.climbing = true;
+            }
+            if player.climbing {
+                if !on_ladder || (physics.contacts.bottom && cy < -0.5) {
+                    player.climbing = false;
+                } else if player.jump_buffer > 0. {
+                    player.climbing = false;
+                    physics.is_jumping = true;
+                    physics.velocity.y = tuning.jump_velocity;
+                    player.jump_buffer = 0.;
+                    player.jump_spent = true;
+                    game_events.single_write(GameEvent::Jumped { entity });
+                    continue;
+                } else {
+                    physics.gravity_scale = 0.;
+                    physics.acceleration.x = 0.;
+                    physics.velocity = Vector3::new(0., cy as f32*climb_tuning.speed, 0.);
+                    physics.is_jumping = false;
+                    player.jump_spent = false;
+                    player.air_jumps_used = 0;
+                    continue;
+                }
+            }
+
+            //ignore steering for a moment after a wall jump so the kick off carries
+            player.wall_jump_lock = (player.wall_jump_lock - dt).max(0.);
+            let cx = if player.wall_jump_lock > 0. { 0. } else { cx as f32 };
+            physics.acceleration.x = cx * tuning.run_acceleration;
+            //pressing into a wall while falling slides down it
+            let wall = if physics.contacts.left && cx < 0. {
+                -1.
+            } else if physics.contacts.right && cx > 0. {
+                1.
+            } else {
+                0.
+            };
+            player.wall_sliding = wall != 0. && !physics.contacts.bottom && physics.velocity.y <= 0.;
+            physics.max_fall_speed = if player.wall_sliding { Some(wall_tuning.slide_speed) } else { None };
+            if physics.contacts.bottom {
+                player.jump_spent = false;
+            }
+            //touching down or grabbing a wall restores air jumps
+            if physics.contacts.bottom || player.wall_sliding {
+                player.air_jumps_used = 0;
+            }
+
+            //down on the ground crouches, adding jump while running turns it into a slide
+            let down = cy < -0.5;
+            let was_low = player.sliding || player.crouching;
+            if !player.sliding && physics.contacts.bottom && down && player.jump_buffer > 0. && physics.velocity.x.abs() > 6. && !player.is_attacking {
+                player.sliding = true;
+                player.slide_time = 0.;
+                player.jump_buffer = 0.;
+                physics.velocity.x = physics.facing*physics.velocity.x.abs().max(slide_tuning.speed);
+            }
+            physics.friction_scale = 1.;
+            if player.sliding {
+                player.slide_time += dt;
+                let t = (player.slide_time/slide_tuning.duration).min(1.);
+                physics.friction_scale = slide_tuning.start_friction + (slide_tuning.end_friction - slide_tuning.start_friction)*t;
+                if physics.velocity.x.abs() < slide_tuning.min_speed || !physics.contacts.bottom {
+                    player.sliding = false;
+                }
+            }
+            //stay down while the standing box would end up in the ceiling
+            let blocked = was_low && tilemap.is_solid_in(&character_resource.collision_box(&character_data, name, &AnimationType::Idle).hitbox_at(&physics.position));
+            player.crouching = !player.sliding && physics.contacts.bottom && ((down && !player.is_attacking) || blocked);
+            if was_low && !player.sliding && !player.crouching {
+                //a press made while down shouldn't fire once standing
+                player.jump_buffer = 0.;
+            }
+            if player.sliding || player.crouching {
+                //squeezed under a low ceiling the character can still shuffle out
+                if player.sliding || !blocked {
+                    physics.acceleration.x = 0.;
+                }
+                continue;
+            }
+
+            let can_jump = player.can_jump(physics.airborne_time, jump_tuning);
             if attack && !player.is_attacking  {
                 player.is_attacking = true;
-            } else if jump && !physics.is_jumping && !player.is_attacking && physics.jump_cooldown == 0 {
+            } else if player.jump_buffer > 0. && can_jump && !player.is_attacking {
+                physics.is_jumping = true;
+                physics.velocity.y = tuning.jump_velocity;
+                player.jump_buffer = 0.;
+                player.jump_spent = true;
+                game_events.single_write(GameEvent::Jumped { entity });
+            } else if player.jump_buffer > 0. && physics.in_water && !player.is_attacking {
+                //swim strokes can be repeated as often as jump is pressed
+                physics.velocity.y = physics.velocity.y.max(water_tuning.stroke_velocity);
+                player.jump_buffer = 0.;
+            } else if player.jump_buffer > 0. && player.wall_sliding && !player.is_attacking {
+                //kick away from the wall
                 physics.is_jumping = true;
-                physics.velocity.y = 4.;
+                physics.velocity = Vector3::new(-wall*wall_tuning.jump_velocity.0, wall_tuning.jump_velocity.1, 0.);
+                physics.max_fall_speed = None;
+                physics.facing = -wall;
+                player.jump_buffer = 0.;
+                player.jump_spent = true;
+                player.wall_sliding = false;
+                player.wall_jump_lock = wall_tuning.input_lock;
+                game_events.single_write(GameEvent::Jumped { entity });
+            } else if player.jump_buffer > 0. && can_air_jump && player.air_jumps_used < ability_tuning.air_jumps && !player.is_attacking
+                && !physics.contacts.bottom && physics.airborne_time > jump_tuning.coyote_time {
+                //only once properly airborne, a press near the ground is a normal jump
+                physics.is_jumping = true;
+                physics.velocity.y = ability_tuning.air_jump_velocity;
+                player.jump_buffer = 0.;
+                player.jump_spent = true;
+                player.air_jumps_used += 1;
+                player.air_jumped = true;
+                game_events.single_write(GameEvent::Jumped { entity });
+            }
+            //letting go early makes for a shorter hop
+            if jump_released && player.jump_spent && physics.velocity.y > 0. {
+                physics.velocity.y *= jump_tuning.release_cut;
             }
         }
     }
@@ -19,10 +19,16 @@ pub struct TileMapData {
     version: f32
 }
 
+//tile layers carry data, object groups carry objects
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TileLayerData {
+    #[serde(default)]
     pub data: Vec<usize>,
+    #[serde(default)]
+    pub objects: Vec<MapObjectData>,
+    #[serde(default)]
     pub width: usize,
+    #[serde(default)]
     pub height: usize,
     pub id: usize,
     pub name: String,
@@ -38,3 +44,44 @@ pub struct TileSetSource {
     pub firstgid: usize,
     pub source: String
 }
+
+//rectangle, point or polyline placed in an object group, in tiled pixel coordinates
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapObjectData {
+    pub id: usize,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub r#type: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub polyline: Vec<MapPointData>,
+    #[serde(default)]
+    pub properties: Vec<MapPropertyData>
+}
+impl MapObjectData {
+    pub fn property(&self, name: &str) -> Option<&serde_json::Value> {
+        self.properties.iter()
+            .find(|property| property.name == name)
+            .map(|property| &property.value)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapPointData {
+    pub x: f32,
+    pub y: f32
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapPropertyData {
+    pub name: String,
+    #[serde(default)]
+    pub r#type: String,
+    pub value: serde_json::Value
+}
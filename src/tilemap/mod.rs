@@ -3,8 +3,8 @@ mod json;
 mod resource;
 
 pub use crate::tilemap::json::{
-    TileMapData, TileLayerData, TileSetSource
+    TileMapData, TileLayerData, TileSetSource, MapObjectData
 };
 pub use crate::tilemap::resource::{
-    TileMap
+    TileMap, TileProperty
 };
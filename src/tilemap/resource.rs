@@ -2,6 +2,7 @@
 use amethyst::{
     assets::{Handle},
     core::{
+        math as na,
         transform::{Transform}
     },
     prelude::*,
@@ -13,13 +14,46 @@ use amethyst::{
 
 use std::collections::HashMap;
 
-use super::TileMapData;
+use crate::hitbox::{Hitbox};
+use super::{TileMapData, MapObjectData};
+
+type Point3 = na::Point3<f32>;
+type Vector3 = na::Vector3<f32>;
+
+//gameplay properties given to every tile on a layer with the matching name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TileProperty {
+    Climbable,
+    Water,
+    Ice,
+    Mud,
+    ConveyorLeft,
+    ConveyorRight,
+    Spikes,
+    Lava
+}
+impl TileProperty {
+    pub fn from_layer_name(name: &str) -> Option<TileProperty> {
+        match name {
+            "climbable" => Some(TileProperty::Climbable),
+            "water" => Some(TileProperty::Water),
+            "ice" => Some(TileProperty::Ice),
+            "mud" => Some(TileProperty::Mud),
+            "conveyor_left" => Some(TileProperty::ConveyorLeft),
+            "conveyor_right" => Some(TileProperty::ConveyorRight),
+            "spikes" => Some(TileProperty::Spikes),
+            "lava" => Some(TileProperty::Lava),
+            _ => None
+        }
+    }
+}
 
 #[derive(Default, Debug)]
 pub struct TileMap {
     pub tile_map_data: TileMapData,
     tile_set_handles: HashMap<usize, Handle<SpriteSheet>>,
-    pub collidable_layer: usize
+    pub collidable_layer: usize,
+    property_layers: HashMap<TileProperty, usize>
 }
 impl TileMap {
     pub fn new(tile_map_data: TileMapData, tile_set_handles: HashMap<usize, Handle<SpriteSheet>>) -> TileMap {
@@ -32,10 +66,22 @@ impl TileMap {
                 break;
             }
         }
+        //find which layers mark tile properties, object groups of the same name have no tiles to look up
+        let mut property_layers = HashMap::new();
+        let tile_count = tile_map_data.width*tile_map_data.height;
+        for (layer_idx, layer) in tile_map_data.layers.iter().enumerate() {
+            if layer.data.len() != tile_count {
+                continue;
+            }
+            if let Some(property) = TileProperty::from_layer_name(&layer.name) {
+                property_layers.insert(property, layer_idx);
+            }
+        }
         TileMap {
             collidable_layer: collidable_layer_idx,
             tile_map_data,
-            tile_set_handles
+            tile_set_handles,
+            property_layers
         }
     }
     pub fn build_map(&self, world: &mut World) {
@@ -63,28 +109,81 @@ impl TileMap {
                     sprite_transform.set_translation_xyz(
                         x, (map_height*tile_height) as f32 - y, z
                     );
-                    //find greatest map start index that is less than sprite_number
-                    let sprite_render = {
-                        let map_start_index = self.tile_set_handles.keys()
-                            .filter(|&k| k <= &tile)
-                            .fold(1, |acc, &k| if k>acc { k } else { acc });
-                        SpriteRender {
-                            sprite_sheet: self.tile_set_handles.get(&map_start_index).unwrap().clone(),
-                            sprite_number: tile - map_start_index
-                        }
-                    };
                     //create entity in world
                     world.create_entity()
-                        .with(sprite_render)
+                        .with(self.tile_sprite(tile))
                         .with(sprite_transform)
                         .build();
                 }
             }
         }
     }
+    pub fn tile_sprite(&self, tile: usize) -> SpriteRender {
+        //find greatest map start index that is less than sprite_number
+        let map_start_index = self.tile_set_handles.keys()
+            .filter(|&k| k <= &tile)
+            .fold(1, |acc, &k| if k>acc { k } else { acc });
+        SpriteRender {
+            sprite_sheet: self.tile_set_handles.get(&map_start_index).unwrap().clone(),
+            sprite_number: tile - map_start_index
+        }
+    }
     pub fn is_tile_collidable(&self, i: usize) -> bool {
         let collidable_layer = self.tile_map_data.layers.get(self.collidable_layer).unwrap();
-        self.is_valid_position(i) && *collidable_layer.data.get(i).unwrap() > 0
+        self.is_valid_position(i) && collidable_layer.data.get(i).map_or(false, |&tile| tile > 0)
+    }
+    //cell coordinates as produced by pix_to_map, anything outside the map is empty
+    pub fn cell_index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.tile_map_data.width || y as usize > self.tile_map_data.height {
+            return None;
+        }
+        let i = self.xy_to_i(x as usize, y as usize);
+        if self.is_valid_position(i) { Some(i) } else { None }
+    }
+    pub fn is_cell_collidable(&self, x: i32, y: i32) -> bool {
+        self.cell_index(x, y).map_or(false, |i| self.is_tile_collidable(i))
+    }
+    pub fn has_property(&self, property: TileProperty, x: i32, y: i32) -> bool {
+        match (self.property_layers.get(&property), self.cell_index(x, y)) {
+            (Some(&layer_idx), Some(i)) => self.tile_map_data.layers[layer_idx].data.get(i).map_or(false, |&tile| tile > 0),
+            _ => false
+        }
+    }
+    pub fn has_property_at(&self, property: TileProperty, pix_x: f32, pix_y: f32) -> bool {
+        let (tile_width, tile_height) = self.tile_size();
+        self.has_property(property, (pix_x/tile_width).floor() as i32, (pix_y/tile_height).floor() as i32)
+    }
+    //whether any tile the box overlaps carries the property
+    pub fn has_property_in(&self, property: TileProperty, hitbox: &Hitbox) -> bool {
+        let (tile_width, tile_height) = self.tile_size();
+        let (x_min, x_max) = (
+            (hitbox.position.x/tile_width).floor() as i32, ((hitbox.position.x + hitbox.size.x)/tile_width).ceil() as i32
+        );
+        let (y_min, y_max) = (
+            ((hitbox.position.y - hitbox.size.y)/tile_height).floor() as i32, (hitbox.position.y/tile_height).ceil() as i32
+        );
+        (x_min..x_max).any(|x| (y_min..y_max).any(|y| self.has_property(property, x, y)))
+    }
+    //whether the box overlaps any solid tile, boxes only touching a tile edge don't count
+    pub fn is_solid_in(&self, hitbox: &Hitbox) -> bool {
+        let (tile_width, tile_height) = self.tile_size();
+        let inset = 0.01;
+        let (x_min, x_max) = (
+            ((hitbox.position.x + inset)/tile_width).floor() as i32, ((hitbox.position.x + hitbox.size.x - inset)/tile_width).floor() as i32
+        );
+        let (y_min, y_max) = (
+            ((hitbox.position.y - hitbox.size.y + inset)/tile_height).floor() as i32, ((hitbox.position.y - inset)/tile_height).floor() as i32
+        );
+        (x_min..=x_max).any(|x| (y_min..=y_max).any(|y| self.is_cell_collidable(x, y)))
+    }
+    //the surface material marked on the tile at a pixel position, if any
+    pub fn surface_at(&self, pix_x: f32, pix_y: f32) -> Option<TileProperty> {
+        [TileProperty::Ice, TileProperty::Mud, TileProperty::ConveyorLeft, TileProperty::ConveyorRight].iter()
+            .cloned()
+            .find(|&property| self.has_property_at(property, pix_x, pix_y))
+    }
+    pub fn tile_size(&self) -> (f32, f32) {
+        (self.tile_map_data.tilewidth as f32, self.tile_map_data.tileheight as f32)
     }
     pub fn is_valid_position(&self, i: usize) -> bool {
         i < self.tile_map_data.width * self.tile_map_data.height
@@ -95,4 +194,109 @@ impl TileMap {
     pub fn pix_to_map(&self, pix: f32) -> usize {
         (pix / self.tile_map_data.tilewidth as f32).floor() as usize
     }
+    //area covered by the drawn tiles, sprites sit a tile left of and below their collision cell
+    pub fn bounds(&self) -> Hitbox {
+        let (tile_width, tile_height) = self.tile_size();
+        let (width, height) = (self.tile_map_data.width as f32*tile_width, self.tile_map_data.height as f32*tile_height);
+        Hitbox {
+            position: Point3::new(-tile_width, height, 0.),
+            size: Vector3::new(width, height, 0.)
+        }
+    }
+    //tiled measures y down from the top, lined up with the rows used for collision
+    pub fn tiled_to_world(&self, x: f32, y: f32) -> Vector3 {
+        let (_, tile_height) = self.tile_size();
+        Vector3::new(x, (self.tile_map_data.height + 1) as f32*tile_height - y, 0.)
+    }
+    //every object of the given type across all object groups
+    pub fn objects(&self, object_type: &str) -> Vec<&MapObjectData> {
+        self.tile_map_data.layers.iter()
+            .flat_map(|layer| layer.objects.iter())
+            .filter(|object| object.r#type == object_type)
+            .collect()
+    }
+    pub fn object_named(&self, name: &str) -> Option<&MapObjectData> {
+        self.tile_map_data.layers.iter()
+            .flat_map(|layer| layer.objects.iter())
+            .find(|object| object.name == name)
+    }
+    //polyline points in world coordinates
+    pub fn object_path(&self, object: &MapObjectData) -> Vec<Vector3> {
+        object.polyline.iter()
+            .map(|point| self.tiled_to_world(object.x + point.x, object.y + point.y))
+            .collect()
+    }
+    pub fn object_hitbox(&self, object: &MapObjectData) -> Hitbox {
+        let top_left = self.tiled_to_world(object.x, object.y);
+        Hitbox {
+            position: Point3::new(top_left.x, top_left.y, 0.),
+            size: Vector3::new(object.width, object.height, 0.)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::{TileLayerData};
+
+    fn layer(name: &str, data: Vec<usize>) -> TileLayerData {
+        let mut layer = TileLayerData::default();
+        layer.name = name.to_string();
+        layer.data = data;
+        layer
+    }
+
+    //4x2 tiles of 16 pixels, cell y 2 is the top row and y 1 the bottom one
+    fn map(layers: Vec<TileLayerData>) -> TileMap {
+        let mut data = TileMapData::default();
+        data.width = 4;
+        data.height = 2;
+        data.tilewidth = 16;
+        data.tileheight = 16;
+        data.layers = layers;
+        TileMap::new(data, Default::default())
+    }
+
+    #[test]
+    fn full_property_layers_mark_their_tiles() {
+        let tilemap = map(vec![
+            layer("collidable", vec![0; 8]),
+            layer("water", vec![0, 0, 0, 0, 1, 1, 0, 0])
+        ]);
+        assert!(tilemap.has_property(TileProperty::Water, 0, 1));
+        assert!(!tilemap.has_property(TileProperty::Water, 2, 1));
+        assert!(!tilemap.has_property(TileProperty::Water, 0, 2));
+        assert!(tilemap.has_property_at(TileProperty::Water, 20., 20.));
+    }
+
+    #[test]
+    fn lookups_outside_the_map_are_empty() {
+        let tilemap = map(vec![
+            layer("collidable", vec![1; 8]),
+            layer("water", vec![1; 8])
+        ]);
+        for &(x, y) in &[(-1, 1), (4, 1), (0, 0), (0, 3)] {
+            assert!(!tilemap.has_property(TileProperty::Water, x, y));
+            assert!(!tilemap.is_cell_collidable(x, y));
+        }
+    }
+
+    #[test]
+    fn object_groups_and_short_layers_carry_no_properties() {
+        let mut water = layer("water", Vec::new());
+        water.objects.push(MapObjectData::default());
+        let tilemap = map(vec![
+            layer("collidable", vec![1, 1, 1]),
+            water,
+            layer("lava", vec![1, 1, 1])
+        ]);
+        for &(x, y) in &[(0, 2), (3, 1)] {
+            assert!(!tilemap.has_property(TileProperty::Water, x, y));
+            assert!(!tilemap.has_property(TileProperty::Lava, x, y));
+        }
+        //a collidable layer missing tiles reads as empty past its end
+        assert!(tilemap.is_cell_collidable(0, 2));
+        assert!(!tilemap.is_cell_collidable(3, 1));
+    }
 }
@@ -0,0 +1,338 @@
+use amethyst::{
+    config::Config,
+    core::{
+        timing::{Time}
+    },
+    ecs::{System},
+    ecs::prelude::{Read, Write}
+};
+use serde::{Serialize, Deserialize};
+
+use std::collections::HashMap;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::character::{CharacterType};
+use crate::tilemap::{TileProperty};
+
+//movement numbers shared by every character, units are pixels and seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhysicsTuning {
+    pub gravity: f32,
+    pub friction: f32,
+    pub run_acceleration: f32,
+    pub jump_velocity: f32
+}
+impl Default for PhysicsTuning {
+    fn default() -> PhysicsTuning {
+        PhysicsTuning {
+            gravity: -588.,
+            friction: 14.,
+            run_acceleration: 720.,
+            jump_velocity: 240.
+        }
+    }
+}
+
+//per character type, anything left out falls back to the shared tuning
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsOverrides {
+    pub gravity: Option<f32>,
+    pub friction: Option<f32>,
+    pub run_acceleration: Option<f32>,
+    pub jump_velocity: Option<f32>
+}
+impl PhysicsOverrides {
+    fn apply(&self, tuning: &PhysicsTuning) -> PhysicsTuning {
+        PhysicsTuning {
+            gravity: self.gravity.unwrap_or(tuning.gravity),
+            friction: self.friction.unwrap_or(tuning.friction),
+            run_acceleration: self.run_acceleration.unwrap_or(tuning.run_acceleration),
+            jump_velocity: self.jump_velocity.unwrap_or(tuning.jump_velocity)
+        }
+    }
+}
+
+//platformer forgiveness windows, all in seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpTuning {
+    //how long after walking off a ledge a jump is still allowed
+    pub coyote_time: f32,
+    //how long a jump pressed in the air is remembered before landing
+    pub buffer_time: f32,
+    //upward velocity is scaled by this when jump is released early
+    pub release_cut: f32
+}
+impl Default for JumpTuning {
+    fn default() -> JumpTuning {
+        JumpTuning {
+            coyote_time: 0.1,
+            buffer_time: 0.15,
+            release_cut: 0.5
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WallTuning {
+    //fastest fall speed while sliding down a wall
+    pub slide_speed: f32,
+    //kick off velocity, x points away from the wall
+    pub jump_velocity: (f32, f32),
+    //seconds horizontal input is ignored after a wall jump
+    pub input_lock: f32
+}
+impl Default for WallTuning {
+    fn default() -> WallTuning {
+        WallTuning {
+            slide_speed: 60.,
+            jump_velocity: (180., 220.),
+            input_lock: 0.15
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbilityTuning {
+    //extra jumps allowed before touching the ground again
+    pub air_jumps: u32,
+    pub air_jump_velocity: f32,
+    pub dash_speed: f32,
+    pub dash_duration: f32,
+    pub dash_cooldown: f32
+}
+impl Default for AbilityTuning {
+    fn default() -> AbilityTuning {
+        AbilityTuning {
+            air_jumps: 1,
+            air_jump_velocity: 200.,
+            dash_speed: 300.,
+            dash_duration: 0.15,
+            dash_cooldown: 0.5
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlideTuning {
+    //minimum speed a slide starts with
+    pub speed: f32,
+    //friction scale eases from start to end over the duration
+    pub start_friction: f32,
+    pub end_friction: f32,
+    pub duration: f32,
+    //the slide ends below this speed
+    pub min_speed: f32
+}
+impl Default for SlideTuning {
+    fn default() -> SlideTuning {
+        SlideTuning {
+            speed: 200.,
+            start_friction: 0.1,
+            end_friction: 1.,
+            duration: 0.5,
+            min_speed: 30.
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClimbTuning {
+    pub speed: f32
+}
+impl Default for ClimbTuning {
+    fn default() -> ClimbTuning {
+        ClimbTuning {
+            speed: 60.
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaterTuning {
+    //multiplies gravity while submerged
+    pub gravity_scale: f32,
+    //upward acceleration while submerged
+    pub buoyancy: f32,
+    //fraction of velocity lost per second
+    pub drag: f32,
+    //upward velocity of a swim stroke
+    pub stroke_velocity: f32,
+    //seconds of air before drowning starts
+    pub breath: f32,
+    pub drown_damage: u32,
+    pub drown_interval: f32
+}
+impl Default for WaterTuning {
+    fn default() -> WaterTuning {
+        WaterTuning {
+            gravity_scale: 0.3,
+            buoyancy: 120.,
+            drag: 3.,
+            stroke_velocity: 150.,
+            breath: 10.,
+            drown_damage: 1,
+            drown_interval: 1.
+        }
+    }
+}
+
+//how the ground changes movement for whatever stands on it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurfaceMaterial {
+    //scales ground friction
+    pub friction: f32,
+    //scales run acceleration
+    pub acceleration: f32,
+    //horizontal speed friction drags bodies toward, non-zero for conveyors
+    pub speed: f32
+}
+impl Default for SurfaceMaterial {
+    fn default() -> SurfaceMaterial {
+        SurfaceMaterial {
+            friction: 1.,
+            acceleration: 1.,
+            speed: 0.
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SurfaceTuning {
+    pub ice: SurfaceMaterial,
+    pub mud: SurfaceMaterial,
+    pub conveyor_left: SurfaceMaterial,
+    pub conveyor_right: SurfaceMaterial
+}
+impl SurfaceTuning {
+    pub fn material(&self, property: TileProperty) -> Option<&SurfaceMaterial> {
+        match property {
+            TileProperty::Ice => Some(&self.ice),
+            TileProperty::Mud => Some(&self.mud),
+            TileProperty::ConveyorLeft => Some(&self.conveyor_left),
+            TileProperty::ConveyorRight => Some(&self.conveyor_right),
+            _ => None
+        }
+    }
+}
+impl Default for SurfaceTuning {
+    fn default() -> SurfaceTuning {
+        SurfaceTuning {
+            ice: SurfaceMaterial { friction: 0.1, acceleration: 0.3, speed: 0. },
+            mud: SurfaceMaterial { friction: 3., acceleration: 0.4, speed: 0. },
+            conveyor_left: SurfaceMaterial { friction: 1., acceleration: 1., speed: -60. },
+            conveyor_right: SurfaceMaterial { friction: 1., acceleration: 1., speed: 60. }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HazardTuning {
+    pub spike_damage: u32,
+    //x points back the way the character was facing
+    pub spike_knockback: (f32, f32),
+    //pixels below the bottom of the map where characters die
+    pub kill_plane_depth: f32
+}
+impl Default for HazardTuning {
+    fn default() -> HazardTuning {
+        HazardTuning {
+            spike_damage: 1,
+            spike_knockback: (120., 220.),
+            kill_plane_depth: 64.
+        }
+    }
+}
+
+//loaded from config/physics.ron
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhysicsConfig {
+    pub tuning: PhysicsTuning,
+    #[serde(default)]
+    pub overrides: HashMap<CharacterType, PhysicsOverrides>,
+    #[serde(default)]
+    pub jump: JumpTuning,
+    #[serde(default)]
+    pub wall: WallTuning,
+    #[serde(default)]
+    pub abilities: AbilityTuning,
+    #[serde(default)]
+    pub slide: SlideTuning,
+    #[serde(default)]
+    pub climb: ClimbTuning,
+    #[serde(default)]
+    pub water: WaterTuning,
+    #[serde(default)]
+    pub surfaces: SurfaceTuning,
+    #[serde(default)]
+    pub hazards: HazardTuning
+}
+impl PhysicsConfig {
+    pub fn tuning(&self, char_type: &CharacterType) -> PhysicsTuning {
+        match self.overrides.get(char_type) {
+            Some(overrides) => overrides.apply(&self.tuning),
+            None => self.tuning.clone()
+        }
+    }
+}
+impl Default for PhysicsConfig {
+    fn default() -> PhysicsConfig {
+        PhysicsConfig {
+            tuning: PhysicsTuning::default(),
+            overrides: HashMap::new(),
+            jump: JumpTuning::default(),
+            wall: WallTuning::default(),
+            abilities: AbilityTuning::default(),
+            slide: SlideTuning::default(),
+            climb: ClimbTuning::default(),
+            water: WaterTuning::default(),
+            surfaces: SurfaceTuning::default(),
+            hazards: HazardTuning::default()
+        }
+    }
+}
+
+//polls a config file and swaps in the new values whenever it is saved
+pub struct ConfigReloadSystem<C> {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    check_timer: f32,
+    config: PhantomData<C>
+}
+impl <C> ConfigReloadSystem<C> {
+    pub fn new(path: PathBuf) -> ConfigReloadSystem<C> {
+        let modified = fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+        ConfigReloadSystem {
+            path,
+            modified,
+            check_timer: 0.,
+            config: PhantomData
+        }
+    }
+}
+impl <'a, C: Config + Default + Send + Sync + 'static> System<'a> for ConfigReloadSystem<C> {
+    type SystemData = (
+        Write<'a, C>,
+        Read<'a, Time>,
+    );
+    fn run(&mut self, (mut config, time): Self::SystemData) {
+        self.check_timer -= time.delta_seconds();
+        if self.check_timer > 0. {
+            return;
+        }
+        self.check_timer = 0.5;
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        //keep the old values if the file is mid-edit or malformed
+        match C::load_no_fallback(&self.path) {
+            Ok(new_config) => *config = new_config,
+            Err(e) => log::warn!("failed to reload {}: {}", self.path.display(), e)
+        }
+    }
+}